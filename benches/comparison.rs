@@ -1,22 +1,23 @@
-use criterion::{criterion_group, criterion_main, AxisScale, BenchmarkGroup, BenchmarkId, Criterion, PlotConfiguration};
-use rand::rngs::StdRng;
-use rand::{RngExt, SeedableRng};
+use criterion::{
+    criterion_group, criterion_main, AxisScale, BatchSize, BenchmarkGroup, BenchmarkId, Criterion, PlotConfiguration,
+};
 use std::hint::black_box;
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use criterion::measurement::Measurement;
-use rust_102::implementations::parallel::step_parallel;
-use rust_102::implementations::pool::{initialise_pool, step_pool};
-use rust_102::implementations::serial::step_serial;
-use rust_102::implementations::workers::step_workers;
+use rust_102::implementations::activity::DEFAULT_TILE_SIZE;
+use rust_102::implementations::{Mode, Tuning};
+use rust_102::seed::{seed_gosper, Soup, Symmetry};
+use rust_102::{Config, Grid};
 
-fn make_seeded(width: usize, height: usize) -> Vec<u8> {
-    let mut rng = StdRng::seed_from_u64(0xDEADBEEF);
-    let mut buf = vec![0u8; width * height];
-    for v in &mut buf {
-        *v = rng.random::<u8>() & 1;
-    }
-    buf
+fn make_seeded(width: usize, height: usize) -> Grid {
+    let soup = Soup { width, height, density: 0.5, symmetry: Symmetry::C1, seed: 0xDEADBEEF };
+    Grid { cells: soup.pattern().cells, width, height }
+}
+
+fn make_gosper(width: usize, height: usize) -> Grid {
+    let mut grid = Grid::new(width, height);
+    seed_gosper(&mut grid.cells, width, height);
+    grid
 }
 
 fn concurrent_group<'a, M: Measurement>(criterion: &'a mut Criterion<M>, name: &str) -> BenchmarkGroup<'a, M> {
@@ -27,128 +28,76 @@ fn concurrent_group<'a, M: Measurement>(criterion: &'a mut Criterion<M>, name: &
     group
 }
 
-fn criterion_benchmark(c: &mut Criterion) {
-    let width = 800usize;
-    let height = 600usize;
-    let total = width * height;
+/// Benchmarks one generation of [mode] from a fresh soup. Each sample builds a new engine in the
+/// untimed setup, so engines with expensive setup (such as spawning a pool) aren't charged for it.
+fn bench_mode<M: Measurement>(group: &mut BenchmarkGroup<M>, id: BenchmarkId, mode: Mode, tuning: Tuning) {
+    bench_grid(group, id, mode, tuning, make_seeded, 0);
+}
 
-    let mut group = c.benchmark_group("step_serial");
+/// Like [bench_mode], but starting from the grid built by [make_grid] and timing the step after
+/// [generation], which the untimed setup runs up to.
+fn bench_grid<M: Measurement>(
+    group: &mut BenchmarkGroup<M>,
+    id: BenchmarkId,
+    mode: Mode,
+    tuning: Tuning,
+    make_grid: fn(usize, usize) -> Grid,
+    generation: u64,
+) {
+    let (width, height) = (800usize, 600usize);
 
-    group.bench_function("step_serial", |b| {
-        b.iter_batched(
+    group.bench_function(id, |bencher| {
+        bencher.iter_batched(
             || {
-                let curr_buffer = make_seeded(width, height);
-                let next_buffer = vec![0u8; total];
-                (curr_buffer, next_buffer)
+                let mut stepper = mode.stepper(make_grid(width, height), &Config::default(), tuning);
+                stepper.step_n(generation);
+                stepper
             },
-            |(curr_buffer, mut next_buffer)| {
-                step_serial(
-                    black_box(&curr_buffer),
-                    black_box(&mut next_buffer),
-                    width,
-                    height,
-                )
+            |mut stepper| {
+                stepper.step();
+                black_box(stepper)
             },
-            criterion::BatchSize::SmallInput,
+            BatchSize::SmallInput,
         )
     });
+}
 
-    group.finish();
-    group = concurrent_group(c, "parallel");
+fn criterion_benchmark(c: &mut Criterion) {
+    let single = Tuning { num_threads: 1, chunk_size: 1, tile_size: DEFAULT_TILE_SIZE };
 
-    for num_threads in (1u32..=7).map(|k| 1usize << k) {
-        group.bench_with_input(
-            BenchmarkId::new("num_threads", num_threads),
-            &num_threads,
-            |bencher, num_threads| {
-                bencher.iter_batched(
-                    || {
-                        let curr_buffer = make_seeded(width, height);
-                        let next_buffer = vec![0u8; total];
-                        (curr_buffer, next_buffer)
-                    },
-                    |(curr_buffer, mut next_buffer)| {
-                        step_parallel(
-                            black_box(&curr_buffer),
-                            black_box(&mut next_buffer),
-                            *num_threads,
-                            width,
-                            height,
-                        )
-                    },
-                    criterion::BatchSize::SmallInput,
-                )
-            },
-        );
-    }
+    let mut group = c.benchmark_group("step_serial");
+    bench_mode(&mut group, BenchmarkId::from_parameter("step_serial"), Mode::Serial, single);
+    group.finish();
 
+    let mut group = c.benchmark_group("step_bitpacked");
+    bench_mode(&mut group, BenchmarkId::from_parameter("step_bitpacked"), Mode::Bitpacked, single);
     group.finish();
-    group = concurrent_group(c, "workers");
 
-    for chunk_size in (1u32..=12).map(|k| 1usize << k) {
-        group.bench_with_input(
-            BenchmarkId::new("chunk_size", chunk_size),
-            &chunk_size,
-            |bencher, chunk_size| {
-                bencher.iter_batched(
-                    || {
-                        let curr_buffer = make_seeded(width, height);
-                        let next_buffer = vec![0u8; total];
-                        (curr_buffer, next_buffer)
-                    },
-                    |(curr_buffer, mut next_buffer)| {
-                        step_workers(
-                            black_box(&curr_buffer),
-                            black_box(&mut next_buffer),
-                            8,
-                            *chunk_size,
-                            width,
-                            height,
-                        )
-                    },
-                    criterion::BatchSize::SmallInput,
-                )
-            },
-        );
+    // A lone Gosper gun leaves most of the grid untouched, so the tile size decides how much of it
+    // is skipped. A single 800x600 tile recomputes everything. Every tile is active in the first
+    // generation, so the step timed is one well after it, with the same gun phase for every size.
+    let mut group = c.benchmark_group("tile_size");
+    for tile_size in [8, 16, 32, 64, 128, 800] {
+        let tuning = Tuning { tile_size, ..single };
+        bench_grid(&mut group, BenchmarkId::from_parameter(tile_size), Mode::Serial, tuning, make_gosper, 60);
     }
-
     group.finish();
-    let mut group = concurrent_group(c, "pool");
-
-    for chunk_size in (1u32..=12).map(|k| 1usize << k) {
-        group.bench_with_input(
-            BenchmarkId::new("chunk_size", chunk_size),
-            &chunk_size,
-            |bencher, &chunk_size| {
-                let curr_buffer = Arc::new(RwLock::new(make_seeded(width, height)));
-                let next_buffer = Arc::new(Mutex::new(vec![0u8; total]));
-                let pool = initialise_pool(
-                    Arc::clone(&curr_buffer),
-                    Arc::clone(&next_buffer),
-                    8,
-                    chunk_size,
-                    width,
-                    height,
-                );
-
-                bencher.iter_custom(|iters| {
-                    let start = Instant::now();
-                    for _ in 0..iters {
-                        step_pool(
-                            black_box(&pool),
-                            black_box(&curr_buffer),
-                            black_box(&next_buffer),
-                        );
-                    }
-                    start.elapsed()
-                });
 
-                drop(pool);
-            },
-        );
+    let mut group = concurrent_group(c, "parallel");
+    for num_threads in (1u32..=7).map(|k| 1usize << k) {
+        let tuning = Tuning { num_threads, chunk_size: 1, tile_size: DEFAULT_TILE_SIZE };
+        bench_mode(&mut group, BenchmarkId::new("num_threads", num_threads), Mode::Parallel, tuning);
     }
-
     group.finish();
+
+    for (name, mode) in [("workers", Mode::Workers), ("pool", Mode::Pool)] {
+        let mut group = concurrent_group(c, name);
+        for chunk_size in (1u32..=12).map(|k| 1usize << k) {
+            let tuning = Tuning { num_threads: 8, chunk_size, tile_size: DEFAULT_TILE_SIZE };
+            bench_mode(&mut group, BenchmarkId::new("chunk_size", chunk_size), mode, tuning);
+        }
+        group.finish();
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,96 @@
+//! Boundary conditions, deciding what lies beyond the edges of a finite grid.
+
+use clap::ValueEnum;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Boundary {
+    /// Everything beyond the edges is permanently dead.
+    Dead,
+    /// Both axes wrap around, so leaving one edge re-enters from the opposite one.
+    #[default]
+    Torus,
+    /// Like the torus, but crossing the top or bottom edge also mirrors the horizontal coordinate.
+    Klein,
+    /// The projective plane: crossing any edge re-enters from the opposite edge, mirrored.
+    CrossSurface,
+    /// The edges act as mirrors, so cells just outside reflect the cells just inside.
+    Mirror,
+    /// Only the horizontal axis wraps around; the top and bottom edges are dead.
+    Cylinder,
+}
+
+impl Boundary {
+    /// Maps a possibly out-of-range coordinate onto a cell of a [width] by [height] grid, or
+    /// returns [None] if the coordinate lies beyond a dead edge.
+    pub fn resolve(self, x: isize, y: isize, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (w, h) = (width as isize, height as isize);
+        let inside = |value: isize, max: isize| (0..max).contains(&value).then_some(value as usize);
+        if let (Some(x), Some(y)) = (inside(x, w), inside(y, h)) {
+            return Some((x, y));
+        }
+
+        // A twist mirrors a coordinate for every odd number of times the other axis has wrapped.
+        let twist = |value: usize, max: usize, crossings: isize| if crossings % 2 == 0 { value } else { max - 1 - value };
+
+        match self {
+            Boundary::Dead => Some((inside(x, w)?, inside(y, h)?)),
+            Boundary::Torus => Some((wrap(x, width), wrap(y, height))),
+            Boundary::Klein => Some((twist(wrap(x, width), width, y.div_euclid(h)), wrap(y, height))),
+            Boundary::CrossSurface => Some((
+                twist(wrap(x, width), width, y.div_euclid(h)),
+                twist(wrap(y, height), height, x.div_euclid(w)),
+            )),
+            Boundary::Mirror => Some((reflect(x, width), reflect(y, height))),
+            Boundary::Cylinder => Some((wrap(x, width), inside(y, h)?)),
+        }
+    }
+}
+
+/// Wraps a signed coordinate around the grid dimensions, allowing for toroidal addressing.
+pub(crate) fn wrap(value: isize, max: usize) -> usize {
+    value.rem_euclid(max as isize) as usize
+}
+
+/// Reflects a signed coordinate back into range, treating both edges as mirrors.
+fn reflect(value: isize, max: usize) -> usize {
+    let folded = value.rem_euclid(2 * max as isize) as usize;
+    if folded < max { folded } else { 2 * max - 1 - folded }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_corners_and_edges_of_a_5x4_grid() {
+        use Boundary::*;
+        let cases = [
+            // Inside the grid, every boundary leaves the cell where it is.
+            (Dead, (2, 1), Some((2, 1))), (Klein, (4, 3), Some((4, 3))), (Mirror, (0, 0), Some((0, 0))),
+            (Dead, (-1, 0), None), (Dead, (4, 4), None),
+            (Torus, (-1, -1), Some((4, 3))), (Torus, (5, 4), Some((0, 0))), (Torus, (1, -1), Some((1, 3))),
+            // Crossing the top or bottom edge mirrors x; crossing the sides does not touch y.
+            (Klein, (-1, -1), Some((0, 3))), (Klein, (5, -1), Some((4, 3))),
+            (Klein, (-1, 4), Some((0, 0))), (Klein, (5, 4), Some((4, 0))),
+            (Klein, (-1, 2), Some((4, 2))), (Klein, (5, 2), Some((0, 2))),
+            (Klein, (1, -1), Some((3, 3))), (Klein, (1, 4), Some((3, 0))),
+            (Klein, (1, 8), Some((1, 0))),
+            // Crossing any edge mirrors the other coordinate.
+            (CrossSurface, (-1, -1), Some((0, 0))), (CrossSurface, (5, -1), Some((4, 0))),
+            (CrossSurface, (-1, 4), Some((0, 3))), (CrossSurface, (5, 4), Some((4, 3))),
+            (CrossSurface, (-1, 1), Some((4, 2))), (CrossSurface, (5, 1), Some((0, 2))),
+            (CrossSurface, (1, -1), Some((3, 3))), (CrossSurface, (1, 4), Some((3, 0))),
+            // The cell just past an edge is the one on it, and the next is the one inside that.
+            (Mirror, (-1, -1), Some((0, 0))), (Mirror, (5, 4), Some((4, 3))),
+            (Mirror, (-1, 2), Some((0, 2))), (Mirror, (2, 4), Some((2, 3))),
+            (Mirror, (-2, 5), Some((1, 2))), (Mirror, (9, -8), Some((0, 0))),
+            // Only the sides wrap.
+            (Cylinder, (-1, 2), Some((4, 2))), (Cylinder, (5, 2), Some((0, 2))),
+            (Cylinder, (-1, 3), Some((4, 3))), (Cylinder, (-1, -1), None),
+            (Cylinder, (2, -1), None), (Cylinder, (5, 4), None),
+        ];
+        for (boundary, (x, y), expected) in cases {
+            assert_eq!(boundary.resolve(x, y, 5, 4), expected, "({x}, {y}) with the {boundary:?} boundary");
+        }
+    }
+}
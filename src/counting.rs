@@ -0,0 +1,162 @@
+//! Neighbour counting. Small neighbourhoods are counted cell by cell, but the extended Larger than
+//! Life neighbourhoods can hold tens of thousands of cells, so those are counted from a summed-area
+//! table built once per generation.
+
+use crate::boundary::Boundary;
+use crate::neighbourhood::Neighbourhood;
+use crate::{idx, Config};
+
+/// Counts alive neighbours in one generation of a grid. Build one with [NeighbourCounter::new]
+/// before each step, and share it between every thread stepping that generation.
+pub enum NeighbourCounter {
+    /// Visits each neighbour in turn.
+    Direct,
+    /// Looks the counts up in a table.
+    Table(SummedAreaTable),
+}
+
+impl NeighbourCounter {
+    /// Prepares to count neighbours in the [width] by [height] [grid] under [config]. This is free
+    /// unless the neighbourhood is an extended one, when it builds a [SummedAreaTable].
+    pub fn new(config: &Config, grid: &[u8], width: usize, height: usize) -> Self {
+        match config.rule.neighbourhood() {
+            Neighbourhood::ExtendedMoore(range) | Neighbourhood::ExtendedVonNeumann(range) => {
+                NeighbourCounter::Table(SummedAreaTable::new(grid, width, height, range as usize, config.boundary))
+            }
+            _ => NeighbourCounter::Direct,
+        }
+    }
+
+    /// Counts the alive neighbours of the cell at ([x], [y]), including the cell itself if the rule
+    /// says so. [grid] must be the grid this counter was built for.
+    pub fn count(&self, config: &Config, grid: &[u8], x: usize, y: usize, width: usize, height: usize) -> u16 {
+        let neighbourhood = config.rule.neighbourhood();
+        let centre = (grid[idx(x, y, width)] == 1) as u16;
+        let around = match self {
+            NeighbourCounter::Direct => neighbor_count(grid, x, y, width, height, neighbourhood, config.boundary),
+            NeighbourCounter::Table(table) => table.count(x, y, neighbourhood) - centre,
+        };
+        if config.rule.includes_centre() { around + centre } else { around }
+    }
+}
+
+/// Counts the number of alive neighbours in [neighbourhood] of the cell at ([x], [y]) in the given
+/// [grid], looking past the edges according to [boundary]. Refractory cells do not count.
+fn neighbor_count(
+    grid: &[u8],
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    neighbourhood: Neighbourhood,
+    boundary: Boundary,
+) -> u16 {
+    let x = x as isize;
+    let y = y as isize;
+
+    neighbourhood
+        .offsets()
+        .fold(0u16, |acc, (dx, dy)| {
+            let Some((nx, ny)) = boundary.resolve(x + dx, y + dy, width, height) else {
+                return acc;
+            };
+            acc + (grid[idx(nx, ny, width)] == 1) as u16
+        })
+}
+
+/// The 3x3 block of cells around ([x], [y]) in [grid], as an isotropic rule's configuration: bit
+/// `3 * (dy + 1) + (dx + 1)` is set when the cell at offset `(dx, dy)` is alive.
+pub(crate) fn configuration(grid: &[u8], x: usize, y: usize, width: usize, height: usize, boundary: Boundary) -> u16 {
+    let (x, y) = (x as isize, y as isize);
+    (0..9).fold(0, |acc, bit| {
+        let alive = boundary
+            .resolve(x + bit % 3 - 1, y + bit / 3 - 1, width, height)
+            .is_some_and(|(x, y)| grid[idx(x, y, width)] == 1);
+        acc | (alive as u16) << bit
+    })
+}
+
+/// Entry `(x, y)` holds the number of alive cells above and to the left of `(x, y)`, so the alive
+/// cells in any rectangle can be counted from its four corners. The grid is padded by [range] cells
+/// on every side, filled in through the boundary, so that rectangles around edge cells need no
+/// special treatment.
+pub struct SummedAreaTable {
+    sums: Vec<u32>,
+    stride: usize,
+    range: usize,
+}
+
+impl SummedAreaTable {
+    fn new(grid: &[u8], width: usize, height: usize, range: usize, boundary: Boundary) -> Self {
+        let (padded_width, padded_height) = (width + 2 * range, height + 2 * range);
+        let stride = padded_width + 1;
+        let mut sums = vec![0u32; stride * (padded_height + 1)];
+
+        for py in 0..padded_height {
+            let mut row = 0;
+            for px in 0..padded_width {
+                let (x, y) = (px as isize - range as isize, py as isize - range as isize);
+                let alive = boundary.resolve(x, y, width, height).is_some_and(|(x, y)| grid[idx(x, y, width)] == 1);
+                row += alive as u32;
+                sums[(py + 1) * stride + px + 1] = sums[py * stride + px + 1] + row;
+            }
+        }
+
+        SummedAreaTable { sums, stride, range }
+    }
+
+    /// The alive cells in the padded columns [left] to [right] and rows [top] to [bottom], with
+    /// exclusive ends.
+    fn sum(&self, left: usize, top: usize, right: usize, bottom: usize) -> u32 {
+        let at = |x: usize, y: usize| self.sums[y * self.stride + x];
+        at(right, bottom) + at(left, top) - at(left, bottom) - at(right, top)
+    }
+
+    /// The alive cells in [neighbourhood] around cell ([x], [y]), including the cell itself. Squares
+    /// take one lookup, and diamonds one per row.
+    fn count(&self, x: usize, y: usize, neighbourhood: Neighbourhood) -> u16 {
+        // In padded coordinates the cell sits at (x + range, y + range).
+        let range = self.range;
+        let total = match neighbourhood {
+            Neighbourhood::ExtendedVonNeumann(_) => (0..=2 * range)
+                .map(|row| {
+                    let half = range - row.abs_diff(range);
+                    self.sum(x + range - half, y + row, x + range + half + 1, y + row + 1)
+                })
+                .sum(),
+            _ => self.sum(x, y, x + 2 * range + 1, y + 2 * range + 1),
+        };
+        total as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::{Soup, Symmetry};
+    use clap::ValueEnum;
+
+    #[test]
+    fn summed_area_counts_match_direct_counts_across_every_boundary() {
+        let (width, height) = (13, 11);
+        let grid = Soup { width, height, density: 0.5, symmetry: Symmetry::C1, seed: 7 }.pattern().cells;
+        for &boundary in Boundary::value_variants() {
+            // The largest range reaches past the top and bottom edges at once.
+            for range in [1, 2, 5, 12] {
+                for shape in ['M', 'N'] {
+                    let rule = format!("R{range},C0,M1,S1,B1,N{shape}").parse().unwrap();
+                    let config = Config { rule, boundary };
+                    let NeighbourCounter::Table(table) = NeighbourCounter::new(&config, &grid, width, height) else {
+                        panic!("R{range} should be counted from a table");
+                    };
+                    let counter = NeighbourCounter::Table(table);
+                    for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+                        let direct = NeighbourCounter::Direct.count(&config, &grid, x, y, width, height);
+                        let table = counter.count(&config, &grid, x, y, width, height);
+                        assert_eq!(table, direct, "({x}, {y}) in R{range} N{shape} with the {boundary:?} boundary");
+                    }
+                }
+            }
+        }
+    }
+}
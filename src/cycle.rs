@@ -0,0 +1,114 @@
+//! Detects when a pattern starts repeating itself: dying out, settling into a still life or an
+//! oscillator, or moving off as a spaceship.
+
+use crate::implementations::sparse::SparseUniverse;
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// A repeating pattern, which looks the same every [Cycle::period] generations from generation
+/// [Cycle::start] on, moved by [Cycle::displacement] each time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: u64,
+    pub period: u64,
+    pub displacement: (i64, i64),
+    /// Whether the pattern has died out, which counts as a cycle of period 1.
+    pub dead: bool,
+}
+
+/// The shapes a pattern has taken so far, by their hashes, with the generation and position at
+/// which each was first seen. Shapes with the same hash are told apart by their cells.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    seen: HashMap<u64, Vec<Seen>>,
+}
+
+/// A shape in a [History], trimmed to its live cells.
+#[derive(Clone, Debug)]
+struct Seen {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>,
+    generation: u64,
+    position: (i64, i64),
+}
+
+impl History {
+    /// Records [pattern] as it is at [generation], which must come after any recorded before. The
+    /// pattern should be trimmed, with its offset giving its position. Returns the cycle it has
+    /// entered if it has taken this shape before, anywhere.
+    pub fn record(&mut self, generation: u64, pattern: &Pattern) -> Option<Cycle> {
+        let mut hasher = DefaultHasher::new();
+        (pattern.width, pattern.height, &pattern.cells).hash(&mut hasher);
+        let (x, y) = pattern.offset.unwrap_or_default();
+
+        let candidates = self.seen.entry(hasher.finish()).or_default();
+        let same = |seen: &&Seen| (seen.width, seen.height, &seen.cells) == (pattern.width, pattern.height, &pattern.cells);
+        let Some(&Seen { generation: start, position: (first_x, first_y), .. }) = candidates.iter().find(same) else {
+            let (width, height, cells) = (pattern.width, pattern.height, pattern.cells.clone());
+            candidates.push(Seen { width, height, cells, generation, position: (x, y) });
+            return None;
+        };
+        let (period, displacement, dead) = (generation - start, (x - first_x, y - first_y), pattern.population() == 0);
+        Some(Cycle { start, period, displacement, dead })
+    }
+}
+
+/// Runs [pattern] under [rule] on an unbounded plane for up to [generations] generations, and
+/// returns the cycle it enters, if it does so in time. The rule must be one the sparse engine can
+/// simulate.
+pub fn find(pattern: &Pattern, rule: Rule, generations: u64) -> Option<Cycle> {
+    let mut universe = SparseUniverse::from_cells(&pattern.cells, pattern.width, pattern.height, rule);
+    let mut history = History::default();
+    let mut cycle = history.record(0, &shape(&universe));
+    while cycle.is_none() && universe.generation() < generations {
+        universe.step(1);
+        cycle = history.record(universe.generation(), &shape(&universe));
+    }
+    cycle
+}
+
+/// The live cells of [universe], trimmed, with their offset from the origin.
+fn shape(universe: &SparseUniverse) -> Pattern {
+    let Some((x, y, width, height)) = universe.bounding_box() else {
+        return Pattern::default();
+    };
+    let mut pattern = Pattern::new(width, height);
+    universe.write_cells(&mut pattern.cells, x, y, width, height);
+    pattern.offset = Some((x, y));
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library;
+
+    #[test]
+    fn finds_the_period_and_displacement_of_a_glider() {
+        let glider = library::get("glider").unwrap().pattern();
+        let cycle = find(&glider, Rule::CONWAY, 100);
+        assert_eq!(cycle, Some(Cycle { start: 0, period: 4, displacement: (1, 1), dead: false }));
+    }
+
+    #[test]
+    fn shapes_sharing_a_hash_are_not_mistaken_for_each_other() {
+        let mut blinker = Pattern::new(3, 1);
+        blinker.cells.fill(1);
+        let mut dot = Pattern::new(3, 1);
+        dot.cells[1] = 1;
+
+        // File the blinker under the dot's hash, as if the two had collided.
+        let mut history = History::default();
+        history.record(0, &blinker);
+        let mut hasher = DefaultHasher::new();
+        (dot.width, dot.height, &dot.cells).hash(&mut hasher);
+        let filed = history.seen.drain().flat_map(|(_, seen)| seen).collect();
+        history.seen.insert(hasher.finish(), filed);
+
+        assert_eq!(history.record(1, &dot), None);
+        assert_eq!(history.record(2, &dot), Some(Cycle { start: 1, period: 1, displacement: (0, 0), dead: false }));
+    }
+}
@@ -1,3 +1,2 @@
 #[allow(dead_code)]
-
 mod borrow;
@@ -0,0 +1,172 @@
+//! Change tracking for the flat-buffer engines. The grid is divided into square tiles, and only
+//! tiles which changed in the last generation, or border one which did, are recomputed; every
+//! other tile is copied across unchanged.
+
+use crate::boundary::Boundary;
+use crate::counting::NeighbourCounter;
+use crate::{next_cell, Config};
+
+/// The default width and height of a tile, in cells.
+pub const DEFAULT_TILE_SIZE: usize = 32;
+
+/// The set of tiles that must be recomputed in the next generation.
+#[derive(Clone, Debug)]
+pub struct ActiveTiles {
+    width: usize,
+    height: usize,
+    tile_size: usize,
+    columns: usize,
+    rows: usize,
+    active: Vec<bool>,
+}
+
+/// A snapshot of how much of the grid an engine is recomputing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ActivityStats {
+    pub active_tiles: usize,
+    pub total_tiles: usize,
+    pub tile_size: usize,
+}
+
+impl ActiveTiles {
+    /// Divides a [width] by [height] grid into tiles of [tile_size] cells square (the last row and
+    /// column may be smaller). Nothing is known about the grid yet, so every tile starts active.
+    pub fn new(width: usize, height: usize, tile_size: usize) -> Self {
+        assert!(tile_size > 0, "Tiles must be at least one cell wide!");
+        let (columns, rows) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+        ActiveTiles { width, height, tile_size, columns, rows, active: vec![true; columns * rows] }
+    }
+
+    pub fn stats(&self) -> ActivityStats {
+        ActivityStats {
+            active_tiles: self.active.iter().filter(|&&active| active).count(),
+            total_tiles: self.active.len(),
+            tile_size: self.tile_size,
+        }
+    }
+
+    /// Whether the tile containing cell ([x], [y]) will be recomputed.
+    pub fn is_active(&self, x: usize, y: usize) -> bool {
+        self.active[(y / self.tile_size) * self.columns + x / self.tile_size]
+    }
+
+    /// Steps the cells from [start] to `start + out.len()` (in row-major order) of [curr] into
+    /// [out], counting neighbours with [counter]. Runs of cells in active tiles are recomputed, and
+    /// runs in inactive tiles copied.
+    pub fn step_range(&self, config: &Config, counter: &NeighbourCounter, curr: &[u8], out: &mut [u8], start: usize) {
+        let (width, height) = (self.width, self.height);
+        let end = start + out.len();
+        let mut cell = start;
+
+        while cell < end {
+            let (x, y) = (cell % width, cell / width);
+            let run_end = (y * width + ((x / self.tile_size + 1) * self.tile_size).min(width)).min(end);
+            let run = &mut out[cell - start..run_end - start];
+
+            if self.is_active(x, y) {
+                for (i, target) in run.iter_mut().enumerate() {
+                    *target = next_cell(config, counter, curr, x + i, y, width, height);
+                }
+            } else {
+                run.copy_from_slice(&curr[cell..run_end]);
+            }
+            cell = run_end;
+        }
+    }
+
+    /// Decides which tiles to recompute next, given the generations before ([previous]) and after
+    /// ([current]) the last step under [config]. A tile that changed is kept active, along with
+    /// every tile holding cells within the neighbourhood's range of it, across the boundary if need
+    /// be.
+    pub fn update(&mut self, previous: &[u8], current: &[u8], config: &Config) {
+        let reach = config.rule.neighbourhood().range();
+        let changed: Vec<(usize, usize)> = (0..self.rows)
+            .flat_map(|ty| (0..self.columns).map(move |tx| (tx, ty)))
+            .filter(|&(tx, ty)| self.active[ty * self.columns + tx] && self.tile_changed(previous, current, tx, ty))
+            .collect();
+
+        self.active.fill(false);
+        for (tx, ty) in changed {
+            self.activate_around(tx, ty, reach, config.boundary);
+        }
+    }
+
+    /// The cells covered by tile ([tx], [ty]), as `(left, top, right, bottom)` with exclusive ends.
+    fn bounds(&self, tx: usize, ty: usize) -> (usize, usize, usize, usize) {
+        let (left, top) = (tx * self.tile_size, ty * self.tile_size);
+        (left, top, (left + self.tile_size).min(self.width), (top + self.tile_size).min(self.height))
+    }
+
+    fn tile_changed(&self, previous: &[u8], current: &[u8], tx: usize, ty: usize) -> bool {
+        let (left, top, right, bottom) = self.bounds(tx, ty);
+        (top..bottom).any(|y| {
+            let row = y * self.width + left..y * self.width + right;
+            previous[row.clone()] != current[row]
+        })
+    }
+
+    /// Activates the tiles holding any cell within [reach] cells of tile ([tx], [ty]).
+    fn activate_around(&mut self, tx: usize, ty: usize, reach: usize, boundary: Boundary) {
+        let (left, top, right, bottom) = self.bounds(tx, ty);
+        let interior = left >= reach && top >= reach && right + reach <= self.width && bottom + reach <= self.height;
+
+        if interior {
+            let size = self.tile_size;
+            for y in (top - reach) / size..=(bottom + reach - 1) / size {
+                for x in (left - reach) / size..=(right + reach - 1) / size {
+                    self.active[y * self.columns + x] = true;
+                }
+            }
+            return;
+        }
+
+        // Tiles on the edge of the grid may neighbour tiles anywhere, depending on the boundary.
+        let reach = reach as isize;
+        for y in top as isize - reach..bottom as isize + reach {
+            for x in left as isize - reach..right as isize + reach {
+                if let Some((x, y)) = boundary.resolve(x, y, self.width, self.height) {
+                    self.active[(y / self.tile_size) * self.columns + x / self.tile_size] = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implementations::{Mode, Tuning};
+    use crate::rule::Rule;
+    use crate::Grid;
+
+    #[test]
+    fn quiet_tiles_are_skipped_without_changing_the_result() {
+        const SIZE: usize = 128;
+        let mut grid = Grid::new(SIZE, SIZE);
+        // A glider heading south-east, and a block well away from its path.
+        for (x, y) in [(41, 60), (42, 61), (40, 62), (41, 62), (42, 62), (100, 20), (101, 20), (100, 21), (101, 21)] {
+            grid.cells[y * SIZE + x] = 1;
+        }
+        let config = Config { rule: Rule::CONWAY, boundary: Boundary::Dead };
+        let tuning = Tuning { num_threads: 1, chunk_size: 1, tile_size: 16 };
+        let mut tiled = Mode::Serial.stepper(grid.clone(), &config, tuning);
+        let mut whole = Mode::Serial.stepper(grid, &config, Tuning { tile_size: SIZE, ..tuning });
+
+        assert_eq!(tiled.activity(), Some(ActivityStats { active_tiles: 64, total_tiles: 64, tile_size: 16 }));
+        tiled.step();
+        whole.step();
+        // Only the glider's tile changed, so only it and its eight neighbours are recomputed.
+        assert_eq!(tiled.activity(), Some(ActivityStats { active_tiles: 9, total_tiles: 64, tile_size: 16 }));
+        assert_eq!(whole.activity(), Some(ActivityStats { active_tiles: 1, total_tiles: 1, tile_size: SIZE }));
+
+        for generation in 2..=200 {
+            tiled.step();
+            whole.step();
+            assert_eq!(*tiled.grid(), *whole.grid(), "generation {generation}");
+            assert!(tiled.activity().unwrap().active_tiles <= 16, "generation {generation}");
+        }
+        let grid = tiled.grid();
+        assert!([(100, 20), (101, 20), (100, 21), (101, 21)].iter().all(|&(x, y)| grid[y * SIZE + x] == 1));
+        assert_eq!(grid.iter().filter(|&&cell| cell == 1).count(), 9);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::implementations::{GridView, Stepper, Tuning};
+use crate::neighbourhood::Neighbourhood;
+use crate::{idx, Config, Grid};
+
+/// A grid storing one cell per bit, 64 cells to a [u64] word. Bit `x % 64` of word `x / 64` in a
+/// row holds the cell in column `x`; each row is padded to a whole number of words, and the
+/// padding bits are always zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitGrid {
+    words: Vec<u64>,
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+}
+
+impl BitGrid {
+    /// Creates an empty (all dead) grid.
+    pub fn new(width: usize, height: usize) -> Self {
+        let words_per_row = width.div_ceil(64);
+        BitGrid { words: vec![0; words_per_row * height], width, height, words_per_row }
+    }
+
+    /// Packs a row-major grid of cells, where any non-zero cell is alive.
+    pub fn from_cells(cells: &[u8], width: usize, height: usize) -> Self {
+        let mut grid = BitGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                grid.set(x, y, cells[idx(x, y, width)] != 0);
+            }
+        }
+        grid
+    }
+
+    /// Unpacks the grid into a row-major buffer of `0`s and `1`s.
+    pub fn write_cells(&self, cells: &mut [u8]) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                cells[idx(x, y, self.width)] = self.get(x, y) as u8;
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.words[y * self.words_per_row + x / 64] & (1 << (x % 64)) != 0
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        let word = &mut self.words[y * self.words_per_row + x / 64];
+        if alive {
+            *word |= 1 << (x % 64);
+        } else {
+            *word &= !(1 << (x % 64));
+        }
+    }
+
+    /// The number of alive cells.
+    pub fn population(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.words_per_row..(y + 1) * self.words_per_row]
+    }
+
+    /// Returns row [y] widened by one ghost cell on either side, so that bit `i` holds column
+    /// `i - 1`. Ghost cells, and whole rows outside the grid, are filled in through the boundary.
+    fn padded_row(&self, y: isize, config: &Config) -> Vec<u64> {
+        let mut padded = vec![0u64; (self.width + 2).div_ceil(64) + 1];
+        let resolve = |x: isize| config.boundary.resolve(x, y, self.width, self.height);
+
+        if (0..self.height as isize).contains(&y) {
+            for (i, &word) in self.row(y as usize).iter().enumerate() {
+                padded[i] |= word << 1;
+                padded[i + 1] |= word >> 63;
+            }
+            for (x, i) in [(-1, 0), (self.width as isize, self.width + 1)] {
+                if resolve(x).is_some_and(|(x, y)| self.get(x, y)) {
+                    padded[i / 64] |= 1 << (i % 64);
+                }
+            }
+        } else {
+            for x in -1..=self.width as isize {
+                if resolve(x).is_some_and(|(x, y)| self.get(x, y)) {
+                    let i = (x + 1) as usize;
+                    padded[i / 64] |= 1 << (i % 64);
+                }
+            }
+        }
+
+        padded
+    }
+}
+
+/// Returns the 64 bits of [row] starting at bit `64 * word + shift`, for a [shift] of 0, 1 or 2.
+fn window(row: &[u64], word: usize, shift: u32) -> u64 {
+    match shift {
+        0 => row[word],
+        _ => row[word] >> shift | row[word + 1] << (64 - shift),
+    }
+}
+
+/// Adds three bit-slices, returning the sum and carry bits.
+fn full_adder(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, a & b | partial & c)
+}
+
+/// Adds two bit-slices, returning the sum and carry bits.
+fn half_adder(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+/// A bit-packed step evaluates 64 cells at once. The eight neighbours of every cell in a word are
+/// lined up as eight words by shifting the rows above, on and below it, and then summed with a
+/// tree of bit-sliced full adders into a 4-bit neighbour count per cell. The rule is applied by
+/// matching those counts against each birth and survival count in turn. A bit can only hold dead
+/// or alive, so the rule must be life-like, and totalistic over the Moore neighbourhood (without
+/// the centre).
+pub fn step_bitpacked(curr: &BitGrid, next: &mut BitGrid, config: &Config) {
+    let (width, height) = (curr.width, curr.height);
+    assert!(next.width == width && next.height == height, "Grids must be the same size!");
+    assert!(config.rule.is_life_like(), "Bit-packed grids only support two-state rules!");
+    assert!(config.rule.is_totalistic(), "Bit-packed grids only support totalistic rules!");
+    assert!(
+        config.rule.neighbourhood() == Neighbourhood::Moore && !config.rule.includes_centre(),
+        "Bit-packed grids only support the Moore neighbourhood!"
+    );
+
+    let births: Vec<u16> = (0..=8).filter(|&n| config.rule.births(n)).collect();
+    let survivals: Vec<u16> = (0..=8).filter(|&n| config.rule.survives(n)).collect();
+    let tail_mask = match width % 64 {
+        0 => u64::MAX,
+        bits => (1 << bits) - 1,
+    };
+
+    let mut above = curr.padded_row(-1, config);
+    let mut middle = curr.padded_row(0, config);
+    for y in 0..height {
+        let below = curr.padded_row(y as isize + 1, config);
+
+        for word in 0..curr.words_per_row {
+            let (top, bottom) = ([0, 1, 2].map(|s| window(&above, word, s)), [0, 1, 2].map(|s| window(&below, word, s)));
+            let (left, alive, right) = (window(&middle, word, 0), window(&middle, word, 1), window(&middle, word, 2));
+
+            let (top_sum, top_carry) = full_adder(top[0], top[1], top[2]);
+            let (bottom_sum, bottom_carry) = full_adder(bottom[0], bottom[1], bottom[2]);
+            let (side_sum, side_carry) = half_adder(left, right);
+            let (ones, ones_carry) = full_adder(top_sum, bottom_sum, side_sum);
+            let (partial_twos, twos_carry) = full_adder(top_carry, bottom_carry, side_carry);
+            let (twos, twos_carry_2) = half_adder(partial_twos, ones_carry);
+            let (fours, eights) = half_adder(twos_carry, twos_carry_2);
+
+            let equals = |n: u16| {
+                let bit = |slice: u64, place: u16| if n & place != 0 { slice } else { !slice };
+                bit(ones, 1) & bit(twos, 2) & bit(fours, 4) & bit(eights, 8)
+            };
+            let born = births.iter().fold(0, |acc, &n| acc | equals(n));
+            let survived = survivals.iter().fold(0, |acc, &n| acc | equals(n));
+
+            let mut result = !alive & born | alive & survived;
+            if word == curr.words_per_row - 1 {
+                result &= tail_mask;
+            }
+            next.words[y * next.words_per_row + word] = result;
+        }
+
+        above = middle;
+        middle = below;
+    }
+}
+
+/// Steps with [step_bitpacked].
+pub struct BitpackedStepper {
+    curr: BitGrid,
+    next: BitGrid,
+    config: Config,
+    generation: u64,
+}
+
+impl Stepper for BitpackedStepper {
+    fn from_grid(grid: Grid, config: &Config, _: Tuning) -> Self {
+        BitpackedStepper {
+            curr: BitGrid::from_cells(&grid.cells, grid.width, grid.height),
+            next: BitGrid::new(grid.width, grid.height),
+            config: *config,
+            generation: 0,
+        }
+    }
+
+    fn step(&mut self) {
+        step_bitpacked(&self.curr, &mut self.next, &self.config);
+        std::mem::swap(&mut self.curr, &mut self.next);
+        self.generation += 1;
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        let mut grid = Grid::new(self.curr.width, self.curr.height);
+        self.curr.write_cells(&mut grid.cells);
+        GridView::owned(grid.cells, grid.width, grid.height)
+    }
+
+    fn population(&self) -> usize {
+        self.curr.population()
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        let mut grid = Grid::new(self.curr.width, self.curr.height);
+        self.curr.write_cells(&mut grid.cells);
+        grid
+    }
+}
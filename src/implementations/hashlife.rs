@@ -0,0 +1,664 @@
+use crate::implementations::{GridView, Stepper, Tuning};
+use crate::macrocell::{self, Macrocell};
+use crate::rule::Rule;
+use crate::{idx, Config, Grid};
+use std::collections::HashMap;
+
+type NodeId = u32;
+
+/// The two leaves, i.e. single cells, are always the first two nodes.
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Once the cache holds this many nodes, unreachable ones are collected before the next step.
+const DEFAULT_NODE_LIMIT: usize = 1 << 22;
+
+/// The deepest the root may grow. The root spans `-2^(level - 1)..2^(level - 1)` on both axes, and
+/// coordinates are shifted by up to the root's width, all of which must fit in an `i64`.
+pub const MAX_LEVEL: u8 = 62;
+
+/// The largest step [HashLife::step_pow2] can take, as a power of two: the root must be at least
+/// eight times as wide as the step.
+pub const MAX_STEP_LOG: u8 = MAX_LEVEL - 3;
+
+/// A square of `2^level` by `2^level` cells, made of four quadrants one level down.
+struct Node {
+    level: u8,
+    /// North-west, north-east, south-west and south-east quadrants. Unused for leaves.
+    children: [NodeId; 4],
+    population: u64,
+    /// The centre of this node, advanced by `2^(level - 2)` generations.
+    result: Option<NodeId>,
+}
+
+/// Gosper's HashLife. The (unbounded) plane is a quadtree whose nodes are canonicalised, so that
+/// identical squares anywhere in space or time are the same node. Each node memoises the result
+/// of advancing its centre, so repetitive patterns can be advanced by exponentially many
+/// generations at a time.
+///
+/// The root is always centred on the origin, spanning `-2^(level - 1)..2^(level - 1)` on both axes.
+pub struct HashLife {
+    nodes: Vec<Node>,
+    lookup: HashMap<[NodeId; 4], NodeId>,
+    /// Results for steps smaller than a node's natural `2^(level - 2)`, keyed by the step's log.
+    partial_results: HashMap<(NodeId, u8), NodeId>,
+    /// The canonical empty node of each level, built on demand.
+    empty: Vec<NodeId>,
+    root: NodeId,
+    rule: Rule,
+    generation: u64,
+    node_limit: usize,
+}
+
+impl HashLife {
+    /// Creates an empty universe evolving under [rule], which must be life-like, since leaves only
+    /// hold dead or alive, and only count adjacent neighbours, since a node's result is computed
+    /// from its own cells.
+    pub fn new(rule: Rule) -> Self {
+        assert!(rule.is_life_like(), "HashLife only supports two-state rules!");
+        assert!(rule.neighbourhood().range() <= 1, "HashLife only supports neighbourhoods of range 1!");
+        let leaf = |population| Node { level: 0, children: [DEAD; 4], population, result: None };
+        let mut universe = HashLife {
+            nodes: vec![leaf(0), leaf(1)],
+            lookup: HashMap::new(),
+            partial_results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            rule,
+            generation: 0,
+            node_limit: DEFAULT_NODE_LIMIT,
+        };
+        universe.root = universe.empty(3);
+        universe
+    }
+
+    /// Imports a row-major grid of cells, where any non-zero cell is alive. The grid is placed so
+    /// that its top-left corner sits at `(-width / 2, -height / 2)`, i.e. it is centred.
+    pub fn from_cells(cells: &[u8], width: usize, height: usize, rule: Rule) -> Self {
+        let mut universe = HashLife::new(rule);
+        let level = (width.max(height).max(8).next_power_of_two().trailing_zeros() + 1) as u8;
+        let half = 1i64 << (level - 1);
+        let (left, top) = (half - (width / 2) as i64, half - (height / 2) as i64);
+        universe.root = universe.build(cells, width, height, level, -left, -top);
+        universe
+    }
+
+    /// Builds the node of [level] whose top-left corner lies at ([x], [y]) in grid coordinates.
+    fn build(&mut self, cells: &[u8], width: usize, height: usize, level: u8, x: i64, y: i64) -> NodeId {
+        let size = 1i64 << level;
+        if x + size <= 0 || y + size <= 0 || x >= width as i64 || y >= height as i64 {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return (cells[idx(x as usize, y as usize, width)] != 0) as NodeId;
+        }
+
+        let half = size / 2;
+        let children = [(0, 0), (half, 0), (0, half), (half, half)]
+            .map(|(dx, dy)| self.build(cells, width, height, level - 1, x + dx, y + dy));
+        self.join(children)
+    }
+
+    /// Imports [macrocell] node by node, without ever drawing it, so it may be far larger than
+    /// would fit in a grid. Its root is centred on the origin, as is the universe's, and any cell
+    /// not dead is alive. The universe starts at the generation the macrocell was saved at.
+    pub fn from_macrocell(macrocell: &Macrocell, rule: Rule) -> Self {
+        let mut universe = HashLife::new(rule);
+        let mut ids = Vec::with_capacity(macrocell.nodes.len());
+        for node in &macrocell.nodes {
+            let id = match *node {
+                macrocell::Node::Square(rows) => universe.square(&rows, 3, 0, 0),
+                macrocell::Node::Branch { level: 1, children } => universe.join(children.map(|state| (state != 0) as NodeId)),
+                macrocell::Node::Branch { level, children } => {
+                    let children = children.map(|child| match child {
+                        0 => universe.empty(level - 1),
+                        child => ids[child as usize - 1],
+                    });
+                    universe.join(children)
+                }
+            };
+            ids.push(id);
+        }
+
+        if let Some(&root) = ids.last() {
+            universe.root = root;
+            while universe.level(universe.root) < 3 {
+                universe.expand();
+            }
+        }
+        universe.generation = macrocell.generation;
+        universe
+    }
+
+    /// Builds the node of [level] whose top-left corner is at ([x], [y]) within an 8x8 square of
+    /// [rows], one bit per cell.
+    fn square(&mut self, rows: &[u8; 8], level: u8, x: usize, y: usize) -> NodeId {
+        if level == 0 {
+            return (rows[y] >> x & 1) as NodeId;
+        }
+        let half = 1 << (level - 1);
+        let children = [(0, 0), (half, 0), (0, half), (half, half)].map(|(dx, dy)| self.square(rows, level - 1, x + dx, y + dy));
+        self.join(children)
+    }
+
+    /// Exports the whole universe as a quadtree, sharing nodes just as the universe does.
+    pub fn to_macrocell(&self) -> Macrocell {
+        let mut macrocell = Macrocell { rule: Some(self.rule), generation: self.generation, ..Macrocell::default() };
+        let mut numbers = HashMap::new();
+        self.export(self.root, &mut macrocell.nodes, &mut numbers);
+        macrocell
+    }
+
+    /// Appends [node] and any of its descendants not yet in [numbers] to [nodes], returning its
+    /// number, or 0 if it is empty.
+    fn export(&self, node: NodeId, nodes: &mut Vec<macrocell::Node>, numbers: &mut HashMap<NodeId, u32>) -> u32 {
+        let Node { level, children, population, .. } = self.nodes[node as usize];
+        if population == 0 {
+            return 0;
+        }
+        if let Some(&number) = numbers.get(&node) {
+            return number;
+        }
+
+        let exported = if level == 3 {
+            let mut rows = [0u8; 8];
+            self.draw_square(node, 0, 0, &mut rows);
+            macrocell::Node::Square(rows)
+        } else {
+            let children = children.map(|child| self.export(child, nodes, numbers));
+            macrocell::Node::Branch { level, children }
+        };
+        nodes.push(exported);
+        numbers.insert(node, nodes.len() as u32);
+        nodes.len() as u32
+    }
+
+    /// Sets the bits of the live cells of [node], whose top-left corner is at ([x], [y]) within an
+    /// 8x8 square of [rows].
+    fn draw_square(&self, node: NodeId, x: usize, y: usize, rows: &mut [u8; 8]) {
+        let Node { level, children, population, .. } = self.nodes[node as usize];
+        if population == 0 {
+            return;
+        }
+        if level == 0 {
+            rows[y] |= 1 << x;
+            return;
+        }
+        let half = 1 << (level - 1);
+        for (child, (dx, dy)) in children.into_iter().zip([(0, 0), (half, 0), (0, half), (half, half)]) {
+            self.draw_square(child, x + dx, y + dy, rows);
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// The number of nodes currently held in the cache.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Sets how many nodes the cache may hold before unreachable nodes are collected.
+    pub fn set_node_limit(&mut self, limit: usize) {
+        self.node_limit = limit;
+    }
+
+    pub fn get_cell(&self, x: i64, y: i64) -> bool {
+        let mut node = self.root;
+        let mut half = 1i64 << (self.level(node) - 1);
+        let (mut x, mut y) = (x + half, y + half);
+        if !(0..2 * half).contains(&x) || !(0..2 * half).contains(&y) {
+            return false;
+        }
+
+        while self.level(node) > 0 {
+            let quadrant = (x >= half) as usize + 2 * (y >= half) as usize;
+            node = self.nodes[node as usize].children[quadrant];
+            x %= half;
+            y %= half;
+            half /= 2;
+        }
+        node == ALIVE
+    }
+
+    pub fn set_cell(&mut self, x: i64, y: i64, alive: bool) {
+        loop {
+            let half = 1i64 << (self.level(self.root) - 1);
+            if (-half..half).contains(&x) && (-half..half).contains(&y) {
+                self.root = self.set_in(self.root, x + half, y + half, alive);
+                return;
+            }
+            self.expand();
+        }
+    }
+
+    /// Returns [node] with the cell at ([x], [y]), relative to its top-left corner, replaced.
+    fn set_in(&mut self, node: NodeId, x: i64, y: i64, alive: bool) -> NodeId {
+        let level = self.level(node);
+        if level == 0 {
+            return alive as NodeId;
+        }
+
+        let half = 1i64 << (level - 1);
+        let quadrant = (x >= half) as usize + 2 * (y >= half) as usize;
+        let mut children = self.nodes[node as usize].children;
+        children[quadrant] = self.set_in(children[quadrant], x % half, y % half, alive);
+        self.join(children)
+    }
+
+    /// Draws the [width] by [height] region of the plane whose top-left corner is at ([x], [y])
+    /// into a row-major buffer of `0`s and `1`s.
+    pub fn write_cells(&self, cells: &mut [u8], x: i64, y: i64, width: usize, height: usize) {
+        cells.fill(0);
+        let half = 1i64 << (self.level(self.root) - 1);
+        self.draw(self.root, -half - x, -half - y, cells, width, height);
+    }
+
+    /// Draws [node], whose top-left corner is at ([x], [y]) relative to the buffer.
+    fn draw(&self, node: NodeId, x: i64, y: i64, cells: &mut [u8], width: usize, height: usize) {
+        let Node { level, children, population, .. } = self.nodes[node as usize];
+        let size = 1i64 << level;
+        if population == 0 || x + size <= 0 || y + size <= 0 || x >= width as i64 || y >= height as i64 {
+            return;
+        }
+        if level == 0 {
+            cells[idx(x as usize, y as usize, width)] = 1;
+            return;
+        }
+
+        let half = size / 2;
+        for (child, (dx, dy)) in children.into_iter().zip([(0, 0), (half, 0), (0, half), (half, half)]) {
+            self.draw(child, x + dx, y + dy, cells, width, height);
+        }
+    }
+
+    /// Advances the universe by exactly `2^log` generations in a single step. Fails, leaving the
+    /// pattern as it was, if [log] is over [MAX_STEP_LOG], if the pattern would have to spread
+    /// beyond a root of [MAX_LEVEL], or if the generation count would overflow.
+    pub fn step_pow2(&mut self, log: u8) -> Result<(), String> {
+        if log > MAX_STEP_LOG {
+            return Err(format!("Cannot step by 2^{log} generations at once: the most is 2^{MAX_STEP_LOG}."));
+        }
+        let generation = self.generation.checked_add(1 << log).ok_or_else(|| {
+            format!("Cannot step by 2^{log} generations from generation {}: the count would overflow.", self.generation)
+        })?;
+        if self.nodes.len() > self.node_limit {
+            self.collect_garbage();
+        }
+
+        // Every live cell must lie within the middle quarter of the root's width, and the step may
+        // be at most an eighth of that width, so that nothing grows out of the centre that is kept.
+        while self.level(self.root) < log + 3 || !self.is_padded() {
+            if self.level(self.root) == MAX_LEVEL {
+                return Err(format!("The pattern has spread too far to step by 2^{log} generations."));
+            }
+            self.expand();
+        }
+
+        self.root = self.successor(self.root, log);
+        self.generation = generation;
+        Ok(())
+    }
+
+    /// Advances the universe by [generations], as a sequence of power-of-two steps, none larger
+    /// than `2^MAX_STEP_LOG`. Fails as [HashLife::step_pow2] does, having taken the steps before.
+    pub fn advance_by(&mut self, generations: u64) -> Result<(), String> {
+        for _ in 0..generations >> MAX_STEP_LOG {
+            self.step_pow2(MAX_STEP_LOG)?;
+        }
+        for log in (0..MAX_STEP_LOG).filter(|&log| generations & (1 << log) != 0) {
+            self.step_pow2(log)?;
+        }
+        Ok(())
+    }
+
+    /// Drops every node not reachable from the root, renumbering those that remain. Memoised
+    /// results are kept only where the result itself survives.
+    pub fn collect_garbage(&mut self) {
+        let mut reachable = vec![false; self.nodes.len()];
+        reachable[DEAD as usize] = true;
+        reachable[ALIVE as usize] = true;
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            if !reachable[node as usize] {
+                reachable[node as usize] = true;
+                if self.level(node) > 0 {
+                    stack.extend(self.nodes[node as usize].children);
+                }
+            }
+        }
+
+        // Children are always created before their parents, so they are renumbered first.
+        let mut renumbered = vec![NodeId::MAX; self.nodes.len()];
+        let mut nodes = Vec::new();
+        for (old, node) in self.nodes.drain(..).enumerate() {
+            if reachable[old] {
+                renumbered[old] = nodes.len() as NodeId;
+                let children = match node.level {
+                    0 => node.children,
+                    _ => node.children.map(|child| renumbered[child as usize]),
+                };
+                nodes.push(Node { children, ..node });
+            }
+        }
+        for node in &mut nodes {
+            node.result = node.result.map(|result| renumbered[result as usize]).filter(|&result| result != NodeId::MAX);
+        }
+
+        self.lookup = nodes.iter().enumerate().skip(2).map(|(id, node)| (node.children, id as NodeId)).collect();
+        self.nodes = nodes;
+        self.partial_results.clear();
+        self.empty.truncate(1);
+        self.root = renumbered[self.root as usize];
+    }
+
+    fn level(&self, node: NodeId) -> u8 {
+        self.nodes[node as usize].level
+    }
+
+    fn children(&self, node: NodeId) -> [NodeId; 4] {
+        self.nodes[node as usize].children
+    }
+
+    /// Returns the canonical node with the given quadrants.
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&node) = self.lookup.get(&children) {
+            return node;
+        }
+
+        let node = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level: self.level(children[0]) + 1,
+            children,
+            population: children.iter().map(|&child| self.nodes[child as usize].population).sum(),
+            result: None,
+        });
+        self.lookup.insert(children, node);
+        node
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = *self.empty.last().unwrap();
+            let node = self.join([below; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Whether every live cell lies within the innermost sixteenth of the root.
+    fn is_padded(&self) -> bool {
+        let [nw, ne, sw, se] = self.children(self.root);
+        let inner = [(nw, 3), (ne, 2), (sw, 1), (se, 0)]
+            .map(|(quadrant, towards_centre)| {
+                let grandchild = self.children(quadrant)[towards_centre];
+                self.nodes[self.children(grandchild)[towards_centre] as usize].population
+            });
+        inner.iter().sum::<u64>() == self.population()
+    }
+
+    /// Doubles the width of the root, keeping its contents centred on the origin.
+    fn expand(&mut self) {
+        let level = self.level(self.root);
+        let empty = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.root = self.join(children);
+    }
+
+    /// The node one level down formed by the centre of [node].
+    fn centre(&mut self, node: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(node);
+        self.join([self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]])
+    }
+
+    /// The node one level down straddling the border between horizontally adjacent [west] and [east].
+    fn horizontal(&mut self, west: NodeId, east: NodeId) -> NodeId {
+        let ([_, wne, _, wse], [enw, _, esw, _]) = (self.children(west), self.children(east));
+        self.join([wne, enw, wse, esw])
+    }
+
+    /// The node one level down straddling the border between vertically adjacent [north] and [south].
+    fn vertical(&mut self, north: NodeId, south: NodeId) -> NodeId {
+        let ([_, _, nsw, nse], [snw, sne, _, _]) = (self.children(north), self.children(south));
+        self.join([nsw, nse, snw, sne])
+    }
+
+    /// Returns the centre of [node], advanced by `2^log` generations, or by `2^(level - 2)` if that
+    /// is smaller.
+    fn successor(&mut self, node: NodeId, log: u8) -> NodeId {
+        let level = self.level(node);
+        let full_speed = log >= level - 2;
+        let memoised = if full_speed {
+            self.nodes[node as usize].result
+        } else {
+            self.partial_results.get(&(node, log)).copied()
+        };
+        if let Some(result) = memoised {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.base_successor(node)
+        } else {
+            // Nine overlapping nodes one level down tile the node with half-node offsets.
+            let [nw, ne, sw, se] = self.children(node);
+            let overlapping = [
+                nw,
+                self.horizontal(nw, ne),
+                ne,
+                self.vertical(nw, sw),
+                self.centre(node),
+                self.vertical(ne, se),
+                sw,
+                self.horizontal(sw, se),
+                se,
+            ];
+            // At full speed the first half of the step happens here, otherwise nothing does.
+            let advanced = overlapping.map(|part| if full_speed { self.successor(part, log) } else { self.centre(part) });
+
+            let quadrants = [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]].map(|indices| {
+                let joined = self.join(indices.map(|i| advanced[i]));
+                self.successor(joined, log)
+            });
+            self.join(quadrants)
+        };
+
+        if full_speed {
+            self.nodes[node as usize].result = Some(result);
+        } else {
+            self.partial_results.insert((node, log), result);
+        }
+        result
+    }
+
+    /// Advances the centre 2x2 cells of a 4x4 node by one generation, cell by cell.
+    fn base_successor(&mut self, node: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (quadrant, &child) in self.children(node).iter().enumerate() {
+            for (leaf_index, &leaf) in self.children(child).iter().enumerate() {
+                let x = 2 * (quadrant % 2) + leaf_index % 2;
+                let y = 2 * (quadrant / 2) + leaf_index / 2;
+                cells[y][x] = leaf == ALIVE;
+            }
+        }
+
+        let neighbourhood = self.rule.neighbourhood();
+        let next = [(1, 1), (2, 1), (1, 2), (2, 2)].map(|(x, y): (isize, isize)| {
+            if let Some(table) = self.rule.table() {
+                return table.next_state(|dx, dy| cells[(y + dy) as usize][(x + dx) as usize] as u8) as NodeId;
+            }
+            let neighbours = neighbourhood
+                .offsets()
+                .filter(|&(dx, dy)| cells[(y + dy) as usize][(x + dx) as usize])
+                .count() as u16;
+            let (x, y) = (x as usize, y as usize);
+            let neighbours = neighbours + (self.rule.includes_centre() && cells[y][x]) as u16;
+            let alive = match self.rule.transitions() {
+                Some(transitions) => {
+                    let configuration = (0..9).fold(0, |acc, bit| acc | (cells[y + bit / 3 - 1][x + bit % 3 - 1] as u16) << bit);
+                    transitions.contains(configuration)
+                }
+                None if cells[y][x] => self.rule.survives(neighbours),
+                None => self.rule.births(neighbours),
+            };
+            alive as NodeId
+        });
+        self.join(next)
+    }
+}
+
+/// Steps with [HashLife], one generation at a time unless asked for more with [Stepper::step_n].
+/// The grid it was built from is centred on the origin, as by [HashLife::from_cells]. Stepping
+/// panics if the universe cannot go that far, see [HashLife::step_pow2].
+pub struct HashLifeStepper {
+    universe: HashLife,
+    width: usize,
+    height: usize,
+}
+
+impl HashLifeStepper {
+    /// Steps [universe], showing the [width] by [height] region centred on the origin as its grid.
+    pub fn from_universe(universe: HashLife, width: usize, height: usize) -> Self {
+        HashLifeStepper { universe, width, height }
+    }
+
+    pub fn universe(&self) -> &HashLife {
+        &self.universe
+    }
+}
+
+impl Stepper for HashLifeStepper {
+    fn from_grid(grid: Grid, config: &Config, _: Tuning) -> Self {
+        let universe = HashLife::from_cells(&grid.cells, grid.width, grid.height, config.rule);
+        HashLifeStepper { universe, width: grid.width, height: grid.height }
+    }
+
+    fn step(&mut self) {
+        self.universe.step_pow2(0).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    fn step_n(&mut self, generations: u64) {
+        self.universe.advance_by(generations).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    fn generation(&self) -> u64 {
+        self.universe.generation()
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.universe.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        let mut grid = Grid::new(self.width, self.height);
+        self.write_region(0, 0, self.width, self.height, &mut grid.cells);
+        GridView::owned(grid.cells, grid.width, grid.height)
+    }
+
+    fn population(&self) -> usize {
+        self.universe.population() as usize
+    }
+
+    fn write_region(&self, x: i64, y: i64, width: usize, height: usize, cells: &mut [u8]) {
+        let (left, top) = ((self.width / 2) as i64, (self.height / 2) as i64);
+        self.universe.write_cells(cells, x - left, y - top, width, height);
+    }
+
+    fn macrocell(&self) -> Option<Macrocell> {
+        Some(self.universe.to_macrocell())
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        let mut grid = Grid::new(self.width, self.height);
+        self.write_region(0, 0, self.width, self.height, &mut grid.cells);
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Boundary;
+    use crate::implementations::activity::DEFAULT_TILE_SIZE;
+    use crate::implementations::serial::SerialStepper;
+
+    const SIZE: usize = 128;
+
+    /// A [SIZE] by [SIZE] grid with [cells] alive, relative to its middle.
+    fn grid(cells: &[(usize, usize)]) -> Grid {
+        let mut grid = Grid::new(SIZE, SIZE);
+        for &(x, y) in cells {
+            grid.cells[idx(SIZE / 2 + x, SIZE / 2 + y, SIZE)] = 1;
+        }
+        grid
+    }
+
+    /// The cells of [universe] where [grid] placed them.
+    fn region(universe: &HashLife) -> Vec<u8> {
+        let mut cells = vec![0; SIZE * SIZE];
+        let half = (SIZE / 2) as i64;
+        universe.write_cells(&mut cells, -half, -half, SIZE, SIZE);
+        cells
+    }
+
+    #[test]
+    fn matches_the_serial_stepper() {
+        let r_pentomino = grid(&[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]);
+        let rule: Rule = "B3/S23".parse().unwrap();
+        let config = Config { rule, boundary: Boundary::Dead };
+        let tuning = Tuning { num_threads: 1, chunk_size: SIZE, tile_size: DEFAULT_TILE_SIZE };
+        let mut serial = SerialStepper::from_grid(r_pentomino.clone(), &config, tuning);
+        let mut universe = HashLife::from_cells(&r_pentomino.cells, SIZE, SIZE, rule);
+
+        // The pattern stays well inside the grid for this long, so the dead boundary never matters.
+        for generations in [1, 2, 5, 8, 37, 47] {
+            serial.step_n(generations);
+            universe.advance_by(generations).unwrap();
+            assert_eq!(universe.generation(), serial.generation());
+            assert_eq!(region(&universe), serial.grid().to_vec(), "generation {}", serial.generation());
+            assert_eq!(universe.population() as usize, serial.population());
+        }
+    }
+
+    #[test]
+    fn moves_a_glider_in_large_steps() {
+        let glider = grid(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut universe = HashLife::from_cells(&glider.cells, SIZE, SIZE, "B3/S23".parse().unwrap());
+        universe.advance_by(4 * 1000).unwrap();
+        assert_eq!(universe.population(), 5);
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            assert!(universe.get_cell(1000 + x, 1000 + y));
+        }
+    }
+
+    #[test]
+    fn refuses_steps_beyond_the_largest_level() {
+        let block = grid(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+        let mut universe = HashLife::from_cells(&block.cells, SIZE, SIZE, "B3/S23".parse().unwrap());
+        assert!(universe.step_pow2(MAX_STEP_LOG + 1).is_err());
+        assert_eq!(universe.generation(), 0);
+
+        // A still life can be carried to the last generation, in several of the largest steps.
+        universe.advance_by(u64::MAX).unwrap();
+        assert_eq!((universe.generation(), universe.population()), (u64::MAX, 4));
+        assert!(universe.step_pow2(0).is_err());
+
+        // A glider has to cross more of the plane than fits in an i64 to get that far.
+        let glider = grid(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut universe = HashLife::from_cells(&glider.cells, SIZE, SIZE, "B3/S23".parse().unwrap());
+        assert!(universe.advance_by(1 << MAX_STEP_LOG).is_ok());
+        assert!(universe.advance_by(u64::MAX).is_err());
+        assert_eq!(universe.population(), 5);
+    }
+}
@@ -2,3 +2,259 @@ pub mod serial;
 pub mod parallel;
 pub mod workers;
 pub mod pool;
+pub mod bitpacked;
+pub mod hashlife;
+pub mod sparse;
+pub mod activity;
+
+use crate::macrocell::Macrocell;
+use crate::neighbourhood::Neighbourhood;
+use crate::rule::Rule;
+use crate::{Config, Grid};
+use activity::{ActiveTiles, ActivityStats};
+use bitpacked::BitpackedStepper;
+use clap::ValueEnum;
+use hashlife::HashLifeStepper;
+use parallel::ParallelStepper;
+use pool::PoolStepper;
+use serial::SerialStepper;
+use sparse::SparseStepper;
+use std::ops::Deref;
+use std::sync::RwLockReadGuard;
+use workers::WorkersStepper;
+
+/// A simulation engine that owns its grid. Engines are interchangeable: any of them can be built
+/// from a [Grid], and can hand it back with [Stepper::into_grid] to be continued by another.
+pub trait Stepper {
+    /// Takes ownership of [grid] to be evolved under [config]. Engines use as much of [tuning]
+    /// as applies to them.
+    fn from_grid(grid: Grid, config: &Config, tuning: Tuning) -> Self
+    where
+        Self: Sized;
+
+    /// Advances the grid by one generation.
+    fn step(&mut self);
+
+    /// Advances the grid by [generations] generations.
+    fn step_n(&mut self, generations: u64) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// The number of generations advanced since construction, counting on from any set with
+    /// [Stepper::set_generation].
+    fn generation(&self) -> u64;
+
+    /// Sets the generation count, so that an engine taking over from another carries on counting
+    /// where it left off.
+    fn set_generation(&mut self, generation: u64);
+
+    /// The current state of the grid. Unbounded engines return the region they were built from.
+    fn grid(&self) -> GridView<'_>;
+
+    /// The number of live cells.
+    fn population(&self) -> usize {
+        self.grid().iter().filter(|&&cell| cell != 0).count()
+    }
+
+    /// Copies the [width] by [height] region whose top-left corner is at ([x], [y]) into [cells].
+    /// The grid occupies the region from `(0, 0)` to its width and height; everything else is dead,
+    /// unless the engine is unbounded.
+    fn write_region(&self, x: i64, y: i64, width: usize, height: usize, cells: &mut [u8]) {
+        let grid = self.grid();
+        for row in 0..height {
+            for column in 0..width {
+                let (gx, gy) = (x + column as i64, y + row as i64);
+                let inside = (0..grid.width as i64).contains(&gx) && (0..grid.height as i64).contains(&gy);
+                cells[row * width + column] = if inside { grid[gy as usize * grid.width + gx as usize] } else { 0 };
+            }
+        }
+    }
+
+    /// How many tiles the engine recomputed in the last generation, for engines that track which
+    /// parts of the grid are changing.
+    fn activity(&self) -> Option<ActivityStats> {
+        None
+    }
+
+    /// The whole universe as a quadtree, for engines that hold one, so that it can be saved without
+    /// being cut down to the grid.
+    fn macrocell(&self) -> Option<Macrocell> {
+        None
+    }
+
+    /// Gives back the grid, stopping the engine.
+    fn into_grid(self: Box<Self>) -> Grid;
+}
+
+/// Settings that change how quickly an engine runs, but never what it computes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tuning {
+    pub num_threads: usize,
+    pub chunk_size: usize,
+    /// The side length of the tiles used to skip unchanging regions, see [ActiveTiles].
+    pub tile_size: usize,
+}
+
+/// A read-only view of an engine's grid, which may borrow the cells, hold a lock on them, or own
+/// a copy of them.
+pub struct GridView<'a> {
+    pub width: usize,
+    pub height: usize,
+    cells: Cells<'a>,
+}
+
+enum Cells<'a> {
+    Borrowed(&'a [u8]),
+    Locked(RwLockReadGuard<'a, Vec<u8>>),
+    Owned(Vec<u8>),
+}
+
+impl<'a> GridView<'a> {
+    pub fn borrowed(cells: &'a [u8], width: usize, height: usize) -> Self {
+        GridView { width, height, cells: Cells::Borrowed(cells) }
+    }
+
+    pub fn locked(cells: RwLockReadGuard<'a, Vec<u8>>, width: usize, height: usize) -> Self {
+        GridView { width, height, cells: Cells::Locked(cells) }
+    }
+
+    pub fn owned(cells: Vec<u8>, width: usize, height: usize) -> Self {
+        GridView { width, height, cells: Cells::Owned(cells) }
+    }
+}
+
+impl Deref for GridView<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.cells {
+            Cells::Borrowed(cells) => cells,
+            Cells::Locked(cells) => cells,
+            Cells::Owned(cells) => cells,
+        }
+    }
+}
+
+/// The available engines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    Serial,
+    Parallel,
+    Workers,
+    Pool,
+    Bitpacked,
+    Hashlife,
+    Sparse,
+}
+
+impl Mode {
+    /// Builds this mode's engine, see [Stepper::from_grid].
+    pub fn stepper(self, grid: Grid, config: &Config, tuning: Tuning) -> Box<dyn Stepper> {
+        match self {
+            Mode::Serial => Box::new(SerialStepper::from_grid(grid, config, tuning)),
+            Mode::Parallel => Box::new(ParallelStepper::from_grid(grid, config, tuning)),
+            Mode::Workers => Box::new(WorkersStepper::from_grid(grid, config, tuning)),
+            Mode::Pool => Box::new(PoolStepper::from_grid(grid, config, tuning)),
+            Mode::Bitpacked => Box::new(BitpackedStepper::from_grid(grid, config, tuning)),
+            Mode::Hashlife => Box::new(HashLifeStepper::from_grid(grid, config, tuning)),
+            Mode::Sparse => Box::new(SparseStepper::from_grid(grid, config, tuning)),
+        }
+    }
+
+    /// Whether the engine can simulate [rule]. Engines storing a single bit per cell cannot hold
+    /// more than two states; the bit-packed adders only count the Moore neighbourhood, and cannot
+    /// tell where the neighbours are. HashLife needs neighbours to be adjacent, and the sparse
+    /// engine needs them to lie within the adjacent tiles; both need empty space to stay empty.
+    pub fn supports(self, rule: &Rule) -> bool {
+        let neighbourhood = rule.neighbourhood();
+        match self {
+            Mode::Bitpacked => {
+                rule.is_life_like()
+                    && rule.is_totalistic()
+                    && neighbourhood == Neighbourhood::Moore
+                    && !rule.includes_centre()
+            }
+            Mode::Hashlife => rule.is_life_like() && rule.is_quiescent() && neighbourhood.range() <= 1,
+            Mode::Sparse => rule.is_quiescent() && neighbourhood.range() <= sparse::TILE_SIZE,
+            _ => true,
+        }
+    }
+
+    /// Whether the engine uses more than one thread.
+    pub fn is_concurrent(self) -> bool {
+        !matches!(self, Mode::Serial | Mode::Bitpacked | Mode::Hashlife)
+    }
+
+    /// Whether the engine simulates the unbounded plane, rather than just its grid.
+    pub fn is_unbounded(self) -> bool {
+        matches!(self, Mode::Hashlife | Mode::Sparse)
+    }
+}
+
+/// The state shared by engines that step from one flat buffer into another and then swap them.
+struct DoubleBuffer {
+    curr: Vec<u8>,
+    next: Vec<u8>,
+    width: usize,
+    height: usize,
+    config: Config,
+    active: ActiveTiles,
+    generation: u64,
+}
+
+impl DoubleBuffer {
+    fn new(grid: Grid, config: &Config, tile_size: usize) -> Self {
+        let next = vec![0; grid.cells.len()];
+        let active = ActiveTiles::new(grid.width, grid.height, tile_size);
+        DoubleBuffer { curr: grid.cells, next, width: grid.width, height: grid.height, config: *config, active, generation: 0 }
+    }
+
+    /// Fills the next buffer from the current one with [step], swaps them, and then works out
+    /// which tiles to recompute next time.
+    fn advance(&mut self, step: impl FnOnce(&[u8], &mut [u8], usize, usize, &Config, &ActiveTiles)) {
+        step(&self.curr, &mut self.next, self.width, self.height, &self.config, &self.active);
+        std::mem::swap(&mut self.curr, &mut self.next);
+        self.active.update(&self.next, &self.curr, &self.config);
+        self.generation += 1;
+    }
+
+    fn view(&self) -> GridView<'_> {
+        GridView::borrowed(&self.curr, self.width, self.height)
+    }
+
+    fn into_grid(self) -> Grid {
+        Grid { cells: self.curr, width: self.width, height: self.height }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Boundary;
+
+    #[test]
+    fn engines_carry_on_from_each_other() {
+        const SIZE: usize = 64;
+        let mut grid = Grid::new(SIZE, SIZE);
+        for (x, y) in [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)] {
+            grid.cells[(SIZE / 2 + y) * SIZE + SIZE / 2 + x] = 1;
+        }
+        let config = Config { rule: Rule::CONWAY, boundary: Boundary::Dead };
+        let tuning = Tuning { num_threads: 2, chunk_size: 256, tile_size: activity::DEFAULT_TILE_SIZE };
+        let mut serial = Mode::Serial.stepper(grid.clone(), &config, tuning);
+
+        // The R-pentomino stays clear of the edges for this long, so the unbounded engines agree.
+        let mut stepper = Mode::Serial.stepper(grid, &config, tuning);
+        for &mode in Mode::value_variants().iter().cycle().skip(1).take(10) {
+            let generation = stepper.generation();
+            stepper = mode.stepper(stepper.into_grid(), &config, tuning);
+            stepper.set_generation(generation);
+            stepper.step_n(3);
+            serial.step_n(3);
+            assert_eq!(stepper.generation(), serial.generation(), "{mode:?}");
+            assert_eq!(*stepper.grid(), *serial.grid(), "{mode:?}");
+        }
+    }
+}
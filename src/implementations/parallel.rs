@@ -1,20 +1,28 @@
-use crate::{advance_cell, idx, neighbor_count};
+use crate::counting::NeighbourCounter;
+use crate::implementations::activity::{ActiveTiles, ActivityStats};
+use crate::implementations::{DoubleBuffer, GridView, Stepper, Tuning};
+use crate::{Config, Grid};
 use std::thread;
 
 /// A parallel step divides the grid into [num_threads] contiguous bands of cells, and each thread
 /// processes one band. Using [core::slice::split_at_mut] allows each thread to write to its own 
-/// portion of the [next_buffer] without needing synchronization primitives.
+/// portion of the [next_buffer] without needing synchronization primitives. Only cells in [active]
+/// tiles are recomputed.
 pub fn step_parallel(
     curr_buffer: &[u8],
     next_buffer: &mut [u8],
     num_threads: usize,
     width: usize,
     height: usize,
+    config: &Config,
+    active: &ActiveTiles,
 ) {
     let total = width * height;
-    let cells_per_worker = (total + num_threads - 1) / num_threads;
+    let cells_per_worker = total.div_ceil(num_threads);
     assert!(num_threads <= total, "More threads than cells! What supercomputer are you using???");
 
+    let counter = &NeighbourCounter::new(config, curr_buffer, width, height);
+
     thread::scope(|scope| {
         let mut left: &mut [u8] = next_buffer;
 
@@ -26,13 +34,46 @@ pub fn step_parallel(
             let (band, tail) = left.split_at_mut(len);
             left = tail;
 
-            scope.spawn(move || {
-                for (i, cell) in (start..end).enumerate() {
-                    let (x, y) = (cell % width, cell / width);
-                    let n = neighbor_count(curr_buffer, x, y, width, height);
-                    band[i] = advance_cell(curr_buffer[idx(x, y, width)], n);
-                }
-            });
+            scope.spawn(move || active.step_range(config, counter, curr_buffer, band, start));
         }
     });
 }
+
+/// Steps with [step_parallel].
+pub struct ParallelStepper {
+    buffer: DoubleBuffer,
+    num_threads: usize,
+}
+
+impl Stepper for ParallelStepper {
+    fn from_grid(grid: Grid, config: &Config, tuning: Tuning) -> Self {
+        ParallelStepper { buffer: DoubleBuffer::new(grid, config, tuning.tile_size), num_threads: tuning.num_threads }
+    }
+
+    fn step(&mut self) {
+        let num_threads = self.num_threads;
+        self.buffer.advance(|curr, next, width, height, config, active| {
+            step_parallel(curr, next, num_threads, width, height, config, active)
+        });
+    }
+
+    fn generation(&self) -> u64 {
+        self.buffer.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.buffer.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        self.buffer.view()
+    }
+
+    fn activity(&self) -> Option<ActivityStats> {
+        Some(self.buffer.active.stats())
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        self.buffer.into_grid()
+    }
+}
@@ -1,12 +1,19 @@
 use std::sync::{Arc, Barrier, Mutex, RwLock};
 use std::{mem, thread};
 use std::sync::atomic::{AtomicBool, Ordering};
-use crate::{advance_cell, idx, neighbor_count};
+use crate::counting::NeighbourCounter;
+use crate::implementations::activity::{ActiveTiles, ActivityStats};
+use crate::implementations::{GridView, Stepper, Tuning};
+use crate::{Config, Grid};
 
 pub struct WorkerPool {
     start_barrier: Arc<Barrier>,
     end_barrier: Arc<Barrier>,
     next_job: Arc<Mutex<usize>>,
+    counter: Arc<RwLock<NeighbourCounter>>,
+    config: Config,
+    width: usize,
+    height: usize,
     threads: Vec<thread::JoinHandle<()>>,
     stop_flag: Arc<AtomicBool>
 }
@@ -21,6 +28,9 @@ impl Drop for WorkerPool {
     }
 }
 
+/// Spawns [num_threads] threads which wait to step [curr_buffer] into [next_buffer], recomputing
+/// only the cells in [active] tiles.
+#[allow(clippy::too_many_arguments)]
 pub fn initialise_pool(
     curr_buffer: Arc<RwLock<Vec<u8>>>,
     next_buffer: Arc<Mutex<Vec<u8>>>,
@@ -28,16 +38,20 @@ pub fn initialise_pool(
     chunk_size: usize,
     width: usize,
     height: usize,
+    config: Config,
+    active: Arc<RwLock<ActiveTiles>>,
 ) -> WorkerPool {
     let next_job: Arc<Mutex<usize>> = Arc::new(Mutex::new(0usize));
     let total = width * height;
     let start_barrier = Arc::new(Barrier::new(num_threads + 1));
     let end_barrier = Arc::new(Barrier::new(num_threads + 1));
     let stop_flag =  Arc::new(AtomicBool::new(false));
+    let counter = Arc::new(RwLock::new(NeighbourCounter::Direct));
 
     let threads = Vec::from_fn(num_threads, |_| {
         let (curr_buffer, next_buffer, next_job, stop_flag) = (Arc::clone(&curr_buffer), Arc::clone(&next_buffer), Arc::clone(&next_job), Arc::clone(&stop_flag));
-        let (start_barrier, end_barrier) = (Arc::clone(&start_barrier), Arc::clone(&end_barrier));
+        let (start_barrier, end_barrier, active) = (Arc::clone(&start_barrier), Arc::clone(&end_barrier), Arc::clone(&active));
+        let counter = Arc::clone(&counter);
         let mut scratch = vec![0u8; chunk_size];
 
         thread::spawn(move || loop {
@@ -46,6 +60,8 @@ pub fn initialise_pool(
                 break;
             }
             let curr_buffer = curr_buffer.read().unwrap();
+            let active = active.read().unwrap();
+            let counter = counter.read().unwrap();
 
             loop {
                 let mut next_job = next_job.lock().unwrap();
@@ -56,12 +72,7 @@ pub fn initialise_pool(
                 *next_job = end;
                 drop(next_job);
 
-                for (index, cell) in (start..end).enumerate() {
-                    let x = cell % width;
-                    let y = cell / width;
-                    let n = neighbor_count(&curr_buffer, x, y, width, height);
-                    scratch[index] = advance_cell(curr_buffer[idx(x, y, width)], n);
-                }
+                active.step_range(&config, &counter, &curr_buffer, &mut scratch[..(end - start)], start);
 
                 next_buffer.lock().unwrap()[start..end].copy_from_slice(&scratch[..(end - start)]);
             }
@@ -70,7 +81,7 @@ pub fn initialise_pool(
         })
     });
 
-    WorkerPool { start_barrier, end_barrier, next_job, threads, stop_flag }
+    WorkerPool { start_barrier, end_barrier, next_job, counter, config, width, height, threads, stop_flag }
 }
 
 pub fn step_pool(
@@ -79,6 +90,7 @@ pub fn step_pool(
     next_buffer: &Arc<Mutex<Vec<u8>>>,
 ) {
     *pool.next_job.lock().unwrap() = 0;
+    *pool.counter.write().unwrap() = NeighbourCounter::new(&pool.config, &curr_buffer.read().unwrap(), pool.width, pool.height);
 
     pool.start_barrier.wait();
     pool.end_barrier.wait();
@@ -86,4 +98,76 @@ pub fn step_pool(
     let mut curr = curr_buffer.write().unwrap();
     let mut next = next_buffer.lock().unwrap();
     mem::swap(&mut *curr, &mut *next);
-}
\ No newline at end of file
+}
+
+/// Steps with [step_pool], keeping the pool alive between generations.
+pub struct PoolStepper {
+    pool: WorkerPool,
+    curr_buffer: Arc<RwLock<Vec<u8>>>,
+    next_buffer: Arc<Mutex<Vec<u8>>>,
+    active: Arc<RwLock<ActiveTiles>>,
+    config: Config,
+    width: usize,
+    height: usize,
+    generation: u64,
+}
+
+impl Stepper for PoolStepper {
+    fn from_grid(grid: Grid, config: &Config, tuning: Tuning) -> Self {
+        let curr_buffer = Arc::new(RwLock::new(grid.cells));
+        let next_buffer = Arc::new(Mutex::new(vec![0u8; grid.width * grid.height]));
+        let active = Arc::new(RwLock::new(ActiveTiles::new(grid.width, grid.height, tuning.tile_size)));
+        let pool = initialise_pool(
+            Arc::clone(&curr_buffer),
+            Arc::clone(&next_buffer),
+            tuning.num_threads,
+            tuning.chunk_size,
+            grid.width,
+            grid.height,
+            *config,
+            Arc::clone(&active),
+        );
+        PoolStepper {
+            pool,
+            curr_buffer,
+            next_buffer,
+            active,
+            config: *config,
+            width: grid.width,
+            height: grid.height,
+            generation: 0,
+        }
+    }
+
+    fn step(&mut self) {
+        step_pool(&self.pool, &self.curr_buffer, &self.next_buffer);
+        // The pool threads are parked at the start barrier, so nothing else holds these locks.
+        let (curr, previous) = (self.curr_buffer.read().unwrap(), self.next_buffer.lock().unwrap());
+        self.active.write().unwrap().update(&previous, &curr, &self.config);
+        self.generation += 1;
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        GridView::locked(self.curr_buffer.read().unwrap(), self.width, self.height)
+    }
+
+    fn activity(&self) -> Option<ActivityStats> {
+        Some(self.active.read().unwrap().stats())
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        let PoolStepper { pool, curr_buffer, width, height, .. } = *self;
+        // Dropping the pool joins its threads, which releases their handles on the buffer.
+        drop(pool);
+        let cells = Arc::into_inner(curr_buffer).expect("Pool threads have stopped.").into_inner().unwrap();
+        Grid { cells, width, height }
+    }
+}
@@ -1,11 +1,51 @@
-use crate::{advance_cell, idx, neighbor_count};
-
-/// A serial step is cell-wise, left-to-right, top-to-bottom.
-pub fn step_serial(curr_buffer: &[u8], next_buffer: &mut [u8], width: usize, height: usize) {
-    for y in 0..height {
-        for x in 0..width {
-            let n = neighbor_count(curr_buffer, x, y, width, height);
-            next_buffer[idx(x, y, width)] = advance_cell(curr_buffer[idx(x, y, width)], n);
-        }
+use crate::counting::NeighbourCounter;
+use crate::implementations::activity::{ActiveTiles, ActivityStats};
+use crate::implementations::{DoubleBuffer, GridView, Stepper, Tuning};
+use crate::{Config, Grid};
+
+/// A serial step is cell-wise, left-to-right, top-to-bottom. Only cells in [active] tiles are
+/// recomputed; the rest are copied.
+pub fn step_serial(
+    curr_buffer: &[u8],
+    next_buffer: &mut [u8],
+    width: usize,
+    height: usize,
+    config: &Config,
+    active: &ActiveTiles,
+) {
+    let counter = NeighbourCounter::new(config, curr_buffer, width, height);
+    active.step_range(config, &counter, curr_buffer, &mut next_buffer[..width * height], 0);
+}
+
+/// Steps with [step_serial].
+pub struct SerialStepper(DoubleBuffer);
+
+impl Stepper for SerialStepper {
+    fn from_grid(grid: Grid, config: &Config, tuning: Tuning) -> Self {
+        SerialStepper(DoubleBuffer::new(grid, config, tuning.tile_size))
+    }
+
+    fn step(&mut self) {
+        self.0.advance(step_serial);
+    }
+
+    fn generation(&self) -> u64 {
+        self.0.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.0.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        self.0.view()
+    }
+
+    fn activity(&self) -> Option<ActivityStats> {
+        Some(self.0.active.stats())
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        self.0.into_grid()
     }
 }
@@ -0,0 +1,338 @@
+use crate::boundary::Boundary;
+use crate::counting::NeighbourCounter;
+use crate::implementations::{GridView, Stepper, Tuning};
+use crate::rule::Rule;
+use crate::{idx, next_cell, Config, Grid};
+use std::collections::HashMap;
+use std::thread;
+
+/// The width and height of a tile, in cells.
+pub const TILE_SIZE: usize = 64;
+
+/// The tile covering cells `TILE_SIZE * x..TILE_SIZE * (x + 1)` horizontally, and similarly for y.
+type TileCoord = (i64, i64);
+
+/// An unbounded plane, stored as a hash map of fixed-size tiles. Only tiles containing live cells
+/// are kept: tiles are created as the pattern grows into them and dropped once they die out.
+pub struct SparseUniverse {
+    tiles: HashMap<TileCoord, Vec<u8>>,
+    rule: Rule,
+    generation: u64,
+}
+
+impl SparseUniverse {
+    /// Creates an empty universe evolving under [rule]. Rules with `B0`, or any other rule under
+    /// which empty space comes alive, would fill the entire plane, so are not supported, and
+    /// neighbours may be at most a tile away.
+    pub fn new(rule: Rule) -> Self {
+        assert!(rule.is_quiescent(), "B0 rules cannot be simulated on an unbounded plane!");
+        assert!(rule.neighbourhood().range() <= TILE_SIZE, "Neighbourhoods may reach at most one tile away!");
+        SparseUniverse { tiles: HashMap::new(), rule, generation: 0 }
+    }
+
+    /// Imports a row-major grid of cells, placing its top-left corner at `(-width / 2, -height / 2)`
+    /// so that it is centred on the origin.
+    pub fn from_cells(cells: &[u8], width: usize, height: usize, rule: Rule) -> Self {
+        let mut universe = SparseUniverse::new(rule);
+        let (left, top) = ((width / 2) as i64, (height / 2) as i64);
+        for y in 0..height {
+            for x in 0..width {
+                let cell = cells[idx(x, y, width)];
+                if cell != 0 {
+                    universe.set_cell(x as i64 - left, y as i64 - top, cell);
+                }
+            }
+        }
+        universe
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> usize {
+        self.tiles.values().map(|tile| tile.iter().filter(|&&cell| cell != 0).count()).sum()
+    }
+
+    /// The number of tiles currently allocated.
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn get_cell(&self, x: i64, y: i64) -> u8 {
+        let (coord, offset) = locate(x, y);
+        self.tiles.get(&coord).map_or(0, |tile| tile[offset])
+    }
+
+    pub fn set_cell(&mut self, x: i64, y: i64, state: u8) {
+        let (coord, offset) = locate(x, y);
+        if state != 0 {
+            self.tiles.entry(coord).or_insert_with(|| vec![0; TILE_SIZE * TILE_SIZE])[offset] = state;
+        } else if let Some(tile) = self.tiles.get_mut(&coord) {
+            tile[offset] = 0;
+        }
+    }
+
+    /// Returns the smallest rectangle `(left, top, width, height)` containing every live cell, or
+    /// [None] if the universe is empty.
+    pub fn bounding_box(&self) -> Option<(i64, i64, usize, usize)> {
+        let (mut left, mut top, mut right, mut bottom) = (i64::MAX, i64::MAX, i64::MIN, i64::MIN);
+        for (&(tx, ty), tile) in &self.tiles {
+            for (offset, _) in tile.iter().enumerate().filter(|&(_, &cell)| cell != 0) {
+                let x = tx * TILE_SIZE as i64 + (offset % TILE_SIZE) as i64;
+                let y = ty * TILE_SIZE as i64 + (offset / TILE_SIZE) as i64;
+                (left, top, right, bottom) = (left.min(x), top.min(y), right.max(x), bottom.max(y));
+            }
+        }
+        (left <= right).then(|| (left, top, (right - left + 1) as usize, (bottom - top + 1) as usize))
+    }
+
+    /// Draws the [width] by [height] region of the plane whose top-left corner is at ([x], [y])
+    /// into a row-major buffer.
+    pub fn write_cells(&self, cells: &mut [u8], x: i64, y: i64, width: usize, height: usize) {
+        cells.fill(0);
+        for (&(tx, ty), tile) in &self.tiles {
+            let (left, top) = (tx * TILE_SIZE as i64 - x, ty * TILE_SIZE as i64 - y);
+            for ly in 0..TILE_SIZE {
+                let row = top + ly as i64;
+                if !(0..height as i64).contains(&row) {
+                    continue;
+                }
+                for lx in 0..TILE_SIZE {
+                    let column = left + lx as i64;
+                    if (0..width as i64).contains(&column) {
+                        cells[idx(column as usize, row as usize, width)] = tile[idx(lx, ly, TILE_SIZE)];
+                    }
+                }
+            }
+        }
+    }
+
+    /// Advances the universe by one generation. Every live tile, and every tile within the
+    /// neighbourhood's range of live cells in one, is a candidate for holding live cells
+    /// afterwards; the candidates are
+    /// divided between [num_threads] threads, which each step their share independently against
+    /// the current (read-only) tiles. Candidates that end up empty are not kept.
+    pub fn step(&mut self, num_threads: usize) {
+        let reach = self.rule.neighbourhood().range();
+        let mut candidates: Vec<TileCoord> = self
+            .tiles
+            .iter()
+            .flat_map(|(&(tx, ty), tile)| {
+                (-1..=1)
+                    .flat_map(move |dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(move |&(dx, dy)| touches(tile, dx, dy, reach))
+                    .map(move |(dx, dy)| (tx + dx, ty + dy))
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let config = Config { rule: self.rule, boundary: Boundary::Dead };
+        let tiles = &self.tiles;
+        let per_thread = candidates.len().div_ceil(num_threads).max(1);
+
+        let stepped: Vec<(TileCoord, Vec<u8>)> = thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(per_thread)
+                .map(|share| {
+                    scope.spawn(move || {
+                        let mut padded = vec![0u8; (TILE_SIZE + 2 * reach) * (TILE_SIZE + 2 * reach)];
+                        share
+                            .iter()
+                            .filter_map(|&coord| {
+                                let tile = step_tile(tiles, coord, &config, reach, &mut padded);
+                                tile.iter().any(|&cell| cell != 0).then_some((coord, tile))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        });
+
+        self.tiles = stepped.into_iter().collect();
+        self.generation += 1;
+    }
+}
+
+/// Splits a cell coordinate into the tile containing it and the offset of the cell in that tile.
+fn locate(x: i64, y: i64) -> (TileCoord, usize) {
+    let size = TILE_SIZE as i64;
+    let coord = (x.div_euclid(size), y.div_euclid(size));
+    (coord, idx(x.rem_euclid(size) as usize, y.rem_euclid(size) as usize, TILE_SIZE))
+}
+
+/// Whether [tile] has live cells within [reach] cells of its neighbour in direction ([dx], [dy]),
+/// or is alive at all for the tile itself.
+fn touches(tile: &[u8], dx: i64, dy: i64, reach: usize) -> bool {
+    let edge = |delta: i64| match delta {
+        -1 => 0..reach,
+        0 => 0..TILE_SIZE,
+        _ => TILE_SIZE - reach..TILE_SIZE,
+    };
+    edge(dy).any(|y| edge(dx).any(|x| tile[idx(x, y, TILE_SIZE)] != 0))
+}
+
+/// Computes the next state of the tile at [coord]. The tile is first copied into [padded] along
+/// with a border [reach] cells wide taken from its neighbours, so that every cell of the tile can
+/// be stepped as an ordinary grid with dead edges.
+fn step_tile(
+    tiles: &HashMap<TileCoord, Vec<u8>>,
+    coord: TileCoord,
+    config: &Config,
+    reach: usize,
+    padded: &mut [u8],
+) -> Vec<u8> {
+    let padded_size = TILE_SIZE + 2 * reach;
+    let block: [[Option<&Vec<u8>>; 3]; 3] =
+        [-1, 0, 1].map(|dy| [-1, 0, 1].map(|dx| tiles.get(&(coord.0 + dx, coord.1 + dy))));
+
+    for py in 0..padded_size {
+        // Shifting padded coordinates by a whole tile puts the previous, this and the next tile at
+        // block indices 0, 1 and 2 respectively.
+        let (row, ly) = ((py + TILE_SIZE - reach) / TILE_SIZE, (py + TILE_SIZE - reach) % TILE_SIZE);
+        for px in 0..padded_size {
+            let (column, lx) = ((px + TILE_SIZE - reach) / TILE_SIZE, (px + TILE_SIZE - reach) % TILE_SIZE);
+            padded[idx(px, py, padded_size)] = block[row][column].map_or(0, |tile| tile[idx(lx, ly, TILE_SIZE)]);
+        }
+    }
+
+    let counter = NeighbourCounter::new(config, padded, padded_size, padded_size);
+    let mut next = vec![0u8; TILE_SIZE * TILE_SIZE];
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            next[idx(x, y, TILE_SIZE)] = next_cell(config, &counter, padded, x + reach, y + reach, padded_size, padded_size);
+        }
+    }
+    next
+}
+
+/// Steps a [SparseUniverse] on [Tuning::num_threads] threads. The grid it was built from is
+/// centred on the origin, as by [SparseUniverse::from_cells].
+pub struct SparseStepper {
+    universe: SparseUniverse,
+    width: usize,
+    height: usize,
+    num_threads: usize,
+}
+
+impl Stepper for SparseStepper {
+    fn from_grid(grid: Grid, config: &Config, tuning: Tuning) -> Self {
+        let universe = SparseUniverse::from_cells(&grid.cells, grid.width, grid.height, config.rule);
+        SparseStepper { universe, width: grid.width, height: grid.height, num_threads: tuning.num_threads }
+    }
+
+    fn step(&mut self) {
+        self.universe.step(self.num_threads);
+    }
+
+    fn generation(&self) -> u64 {
+        self.universe.generation()
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.universe.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        let mut grid = Grid::new(self.width, self.height);
+        self.write_region(0, 0, self.width, self.height, &mut grid.cells);
+        GridView::owned(grid.cells, grid.width, grid.height)
+    }
+
+    fn population(&self) -> usize {
+        self.universe.population()
+    }
+
+    fn write_region(&self, x: i64, y: i64, width: usize, height: usize, cells: &mut [u8]) {
+        let (left, top) = ((self.width / 2) as i64, (self.height / 2) as i64);
+        self.universe.write_cells(cells, x - left, y - top, width, height);
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        let mut grid = Grid::new(self.width, self.height);
+        self.write_region(0, 0, self.width, self.height, &mut grid.cells);
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implementations::Mode;
+
+    /// The live cells of [universe], in raster order.
+    fn live_cells(universe: &SparseUniverse) -> Vec<(i64, i64)> {
+        let Some((left, top, width, height)) = universe.bounding_box() else {
+            return Vec::new();
+        };
+        (top..top + height as i64)
+            .flat_map(|y| (left..left + width as i64).map(move |x| (x, y)))
+            .filter(|&(x, y)| universe.get_cell(x, y) != 0)
+            .collect()
+    }
+
+    #[test]
+    fn gliders_cross_tile_edges_in_both_directions() {
+        // Heading south-east across x = y = 64, and north-west across x = y = 0.
+        let south_east = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let north_west = [(1, 2), (0, 1), (2, 0), (1, 0), (0, 0)];
+        for (glider, start, step) in [(south_east, 58, 1), (north_west, 2, -1)] {
+            let mut universe = SparseUniverse::new(Rule::CONWAY);
+            for (x, y) in glider {
+                universe.set_cell(start + x, start + y, 1);
+            }
+            let expected = live_cells(&universe);
+            for _ in 0..40 {
+                universe.step(1);
+            }
+            let moved: Vec<(i64, i64)> = expected.iter().map(|&(x, y)| (x + 10 * step, y + 10 * step)).collect();
+            assert_eq!(live_cells(&universe), moved);
+        }
+    }
+
+    #[test]
+    fn tiles_are_dropped_once_they_empty() {
+        let mut universe = SparseUniverse::new(Rule::CONWAY);
+        universe.set_cell(-1, -1, 1);
+        universe.set_cell(200, 200, 1);
+        assert_eq!(universe.tile_count(), 2);
+        universe.step(1);
+        assert_eq!(universe.tile_count(), 0);
+
+        // A glider only ever occupies the tiles it overlaps, however far it goes.
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            universe.set_cell(x, y, 1);
+        }
+        for _ in 0..400 {
+            universe.step(1);
+            assert!((1..=4).contains(&universe.tile_count()), "{} tiles", universe.tile_count());
+        }
+        assert_eq!(universe.population(), 5);
+    }
+
+    #[test]
+    fn growth_into_new_tiles_matches_a_large_dead_bounded_grid() {
+        const SIZE: usize = 256;
+        let mut grid = Grid::new(SIZE, SIZE);
+        // An R-pentomino, which throws gliders out in several directions before it settles.
+        for (x, y) in [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)] {
+            grid.cells[(SIZE / 2 + y) * SIZE + SIZE / 2 + x] = 1;
+        }
+        let config = Config { rule: Rule::CONWAY, boundary: Boundary::Dead };
+        let tuning = Tuning { num_threads: 3, chunk_size: 1, tile_size: 16 };
+        let mut serial = Mode::Serial.stepper(grid.clone(), &config, tuning);
+        let mut sparse = SparseStepper::from_grid(grid, &config, tuning);
+        assert_eq!(sparse.universe.tile_count(), 1);
+
+        for generation in 1..=300 {
+            serial.step();
+            sparse.step();
+            if generation % 50 == 0 {
+                assert_eq!(*sparse.grid(), *serial.grid(), "generation {generation}");
+            }
+        }
+        assert!(sparse.universe.tile_count() > 4, "{} tiles", sparse.universe.tile_count());
+    }
+}
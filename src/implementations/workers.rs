@@ -1,10 +1,14 @@
 use std::sync::{Arc, Mutex};
 use std::thread;
-use crate::{advance_cell, idx, neighbor_count};
+use crate::counting::NeighbourCounter;
+use crate::implementations::activity::{ActiveTiles, ActivityStats};
+use crate::implementations::{DoubleBuffer, GridView, Stepper, Tuning};
+use crate::{Config, Grid};
 
 /// Similar to the `parallel` implementation. Each worker thread picks up jobs froma synchronised
 /// counter, incremented by [chunk_size]. Each worker uses a scratch buffer, then writes to
-/// [next_buffer] when the entire chunk is complete.
+/// [next_buffer] when the entire chunk is complete. Only cells in [active] tiles are recomputed.
+#[allow(clippy::too_many_arguments)]
 pub fn step_workers(
     curr_buffer: &[u8],
     next_buffer: &mut [u8],
@@ -12,7 +16,10 @@ pub fn step_workers(
     chunk_size: usize,
     width: usize,
     height: usize,
+    config: &Config,
+    active: &ActiveTiles,
 ) {
+    let counter = &NeighbourCounter::new(config, curr_buffer, width, height);
     let curr_buffer = Arc::new(curr_buffer);
     let next_buffer = Arc::new(Mutex::new(next_buffer));
     let total = width * height;
@@ -33,12 +40,7 @@ pub fn step_workers(
                     *next_job = end;
                     drop(next_job);
 
-                    for (index, cell) in (start..end).enumerate() {
-                        let x = cell % width;
-                        let y = cell / width;
-                        let n = neighbor_count(&curr_buffer, x, y, width, height);
-                        scratch[index] = advance_cell(curr_buffer[idx(x, y, width)], n);
-                    }
+                    active.step_range(config, counter, &curr_buffer, &mut scratch[..(end - start)], start);
 
                     next_buffer.lock().unwrap()[start..end].copy_from_slice(&scratch[..(end - start)]);
                 }
@@ -46,3 +48,42 @@ pub fn step_workers(
         }
     });
 }
+
+/// Steps with [step_workers].
+pub struct WorkersStepper {
+    buffer: DoubleBuffer,
+    tuning: Tuning,
+}
+
+impl Stepper for WorkersStepper {
+    fn from_grid(grid: Grid, config: &Config, tuning: Tuning) -> Self {
+        WorkersStepper { buffer: DoubleBuffer::new(grid, config, tuning.tile_size), tuning }
+    }
+
+    fn step(&mut self) {
+        let Tuning { num_threads, chunk_size, .. } = self.tuning;
+        self.buffer.advance(|curr, next, width, height, config, active| {
+            step_workers(curr, next, num_threads, chunk_size, width, height, config, active)
+        });
+    }
+
+    fn generation(&self) -> u64 {
+        self.buffer.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.buffer.generation = generation;
+    }
+
+    fn grid(&self) -> GridView<'_> {
+        self.buffer.view()
+    }
+
+    fn activity(&self) -> Option<ActivityStats> {
+        Some(self.buffer.active.stats())
+    }
+
+    fn into_grid(self: Box<Self>) -> Grid {
+        self.buffer.into_grid()
+    }
+}
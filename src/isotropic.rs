@@ -0,0 +1,259 @@
+//! Isotropic non-totalistic rules, written in Hensel notation. A letter after a neighbour count
+//! narrows it down to one shape of that many neighbours, up to rotation and reflection: `B2a` only
+//! births cells whose two alive neighbours are adjacent, and `B2-a` all others.
+//!
+//! A configuration is the 3x3 block around a cell, read in raster order into bits 0 to 8, so the
+//! cell itself is bit 4.
+
+use std::fmt::Write;
+
+/// The bit of the centre cell in a configuration.
+pub const CENTRE: u16 = 1 << 4;
+
+/// The bits of the eight neighbours in a configuration.
+const NEIGHBOURS: u16 = 0x1FF & !CENTRE;
+
+/// The letters for each number of neighbours from 0 to 4, in Hensel's order, each with one
+/// configuration of that shape. Counts from 5 to 8 take the letter of the complementary shape.
+const LETTERS: [&[(char, u16)]; 5] = [
+    &[],
+    &[('c', 0x001), ('e', 0x002)],
+    &[('c', 0x005), ('e', 0x00A), ('a', 0x003), ('i', 0x028), ('k', 0x021), ('n', 0x044)],
+    &[
+        ('c', 0x045),
+        ('e', 0x02A),
+        ('a', 0x00B),
+        ('i', 0x007),
+        ('k', 0x062),
+        ('n', 0x00D),
+        ('j', 0x00E),
+        ('q', 0x046),
+        ('r', 0x029),
+        ('y', 0x061),
+    ],
+    &[
+        ('c', 0x145),
+        ('e', 0x0AA),
+        ('a', 0x00F),
+        ('i', 0x02D),
+        ('k', 0x063),
+        ('n', 0x047),
+        ('j', 0x06A),
+        ('q', 0x066),
+        ('r', 0x02B),
+        ('y', 0x065),
+        ('t', 0x069),
+        ('w', 0x04E),
+        ('z', 0x06C),
+    ],
+];
+
+/// The set of configurations whose centre is alive in the next generation. Births are the
+/// configurations with a dead centre, and survivals those with an alive one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Transitions {
+    bits: [u64; 8],
+}
+
+impl Transitions {
+    /// Builds the table from the birth and survival sections of a rulestring, such as `2-a` and
+    /// `12`.
+    pub fn parse(birth: &str, survival: &str) -> Result<Self, String> {
+        let mut transitions = Transitions { bits: [0; 8] };
+        transitions.insert_section(birth, 0)?;
+        transitions.insert_section(survival, CENTRE)?;
+        Ok(transitions)
+    }
+
+    /// Whether a cell in [configuration] is alive in the next generation.
+    pub fn contains(&self, configuration: u16) -> bool {
+        self.bits[configuration as usize / 64] & 1 << (configuration % 64) != 0
+    }
+
+    fn insert(&mut self, configuration: u16) {
+        self.bits[configuration as usize / 64] |= 1 << (configuration % 64);
+    }
+
+    /// Adds every configuration described by [section] to the table, with [centre] set.
+    fn insert_section(&mut self, section: &str, centre: u16) -> Result<(), String> {
+        let mut chars = section.chars().peekable();
+        while let Some(digit) = chars.next() {
+            let count = match digit.to_digit(10) {
+                Some(count) if count <= 8 => count,
+                _ => return Err(format!("Invalid neighbour count '{digit}': expected a digit from 0 to 8.")),
+            };
+            let negated = chars.next_if_eq(&'-').is_some();
+            let mut letters = String::new();
+            while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+                if !letters_for(count).contains(letter) {
+                    return Err(format!("Invalid letter '{letter}' for {count} neighbours."));
+                }
+                letters.push(letter);
+            }
+            if negated && letters.is_empty() {
+                return Err(format!("Expected letters after '{count}-'."));
+            }
+
+            for neighbours in configurations(count) {
+                let shape = letter(neighbours);
+                if letters.is_empty() || shape.is_some_and(|shape| letters.contains(shape)) != negated {
+                    self.insert(neighbours | centre);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether some configuration of [count] neighbours, with the centre alive if [centre] is set,
+    /// is in the table.
+    pub fn any_with(&self, count: u32, centre: bool) -> bool {
+        configurations(count).any(|neighbours| self.contains(neighbours | if centre { CENTRE } else { 0 }))
+    }
+
+    /// Whether each count of neighbours is either always or never enough, wherever they are, so
+    /// that the rule is really an outer-totalistic one.
+    pub fn is_totalistic(&self) -> bool {
+        (0..=8).all(|count| {
+            [0, CENTRE].iter().all(|&centre| {
+                let mut configurations = configurations(count).map(|neighbours| self.contains(neighbours | centre));
+                let first = configurations.next().unwrap();
+                configurations.all(|alive| alive == first)
+            })
+        })
+    }
+
+    /// Writes the birth (or, if [centre] is set, survival) section in Hensel notation, using the
+    /// shorter of listing the shapes present and listing those absent.
+    pub fn format_section(&self, centre: bool) -> String {
+        let centre = if centre { CENTRE } else { 0 };
+        let mut section = String::new();
+        for count in 0..=8 {
+            let letters = letters_for(count);
+            let present: String = letters
+                .chars()
+                .filter(|&shape| configurations(count).any(|n| letter(n) == Some(shape) && self.contains(n | centre)))
+                .collect();
+            let absent: String = letters.chars().filter(|&shape| !present.contains(shape)).collect();
+
+            if letters.is_empty() {
+                if self.contains(configurations(count).next().unwrap() | centre) {
+                    write!(section, "{count}").unwrap();
+                }
+            } else if absent.is_empty() {
+                write!(section, "{count}").unwrap();
+            } else if present.len() > absent.len() {
+                write!(section, "{count}-{absent}").unwrap();
+            } else if !present.is_empty() {
+                write!(section, "{count}{present}").unwrap();
+            }
+        }
+        section
+    }
+}
+
+/// The configurations with a dead centre and exactly [count] alive neighbours.
+fn configurations(count: u32) -> impl Iterator<Item = u16> {
+    (0..=NEIGHBOURS).filter(move |&n| n & CENTRE == 0 && n.count_ones() == count)
+}
+
+/// The letters that can follow [count] in Hensel notation.
+fn letters_for(count: u32) -> String {
+    LETTERS[count.min(8 - count) as usize].iter().map(|&(letter, _)| letter).collect()
+}
+
+/// The letter of the shape formed by [neighbours], or [None] for 0 and 8 neighbours, which have
+/// only one shape each.
+fn letter(neighbours: u16) -> Option<char> {
+    let count = neighbours.count_ones();
+    let neighbours = if count > 4 { !neighbours & NEIGHBOURS } else { neighbours };
+    LETTERS[count.min(8 - count) as usize]
+        .iter()
+        .find(|&&(_, shape)| (0..8).any(|symmetry| transform(shape, symmetry) == neighbours))
+        .map(|&(letter, _)| letter)
+}
+
+/// Applies one of the eight symmetries of the square to a configuration: bit 2 transposes it, and
+/// bits 0 and 1 then mirror it horizontally and vertically.
+fn transform(configuration: u16, symmetry: u8) -> u16 {
+    (0..9).filter(|&i| configuration & 1 << i != 0).fold(0, |acc, i| {
+        let (x, y) = (i % 3, i / 3);
+        let (x, y) = if symmetry & 4 != 0 { (y, x) } else { (x, y) };
+        let x = if symmetry & 1 != 0 { 2 - x } else { x };
+        let y = if symmetry & 2 != 0 { 2 - y } else { y };
+        acc | 1 << (3 * y + x)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implementations::sparse::SparseUniverse;
+    use crate::rule::Rule;
+
+    #[test]
+    fn letters_partition_the_configurations_of_each_count() {
+        for count in 1..=7 {
+            let mut covered = 0;
+            for &(shape, example) in LETTERS[count.min(8 - count) as usize] {
+                let example = if count > 4 { !example & NEIGHBOURS } else { example };
+                assert_eq!(example & CENTRE, 0, "{count}{shape}");
+                assert_eq!(example.count_ones(), count, "{count}{shape}");
+            }
+            for neighbours in configurations(count) {
+                let orbit = |&&(_, example): &&(char, u16)| {
+                    let example = if count > 4 { !example & NEIGHBOURS } else { example };
+                    (0..8).any(|symmetry| transform(example, symmetry) == neighbours)
+                };
+                let shapes = LETTERS[count.min(8 - count) as usize].iter().filter(orbit).count();
+                assert_eq!(shapes, 1, "{neighbours:#05x} has {shapes} shapes among {count} neighbours");
+                covered += 1;
+            }
+            let total = (1..=8).product::<u32>() / ((1..=count).product::<u32>() * (1..=8 - count).product::<u32>());
+            assert_eq!(covered, total, "{count}");
+        }
+        // The counts with a single shape need no letters, and have exactly one configuration.
+        assert!(letters_for(0).is_empty() && letters_for(8).is_empty());
+        assert_eq!(configurations(0).count() + configurations(8).count(), 2);
+    }
+
+    /// The live cells of [cells] after [generations] generations under [rule], in raster order.
+    fn run(rule: &str, cells: &[(i64, i64)], generations: u64) -> Vec<(i64, i64)> {
+        let mut universe = SparseUniverse::new(rule.parse().unwrap());
+        for &(x, y) in cells {
+            universe.set_cell(x, y, 1);
+        }
+        for _ in 0..generations {
+            universe.step(1);
+        }
+        let Some((left, top, width, height)) = universe.bounding_box() else {
+            return Vec::new();
+        };
+        (top..top + height as i64)
+            .flat_map(|y| (left..left + width as i64).map(move |x| (x, y)))
+            .filter(|&(x, y)| universe.get_cell(x, y) != 0)
+            .collect()
+    }
+
+    #[test]
+    fn letters_select_the_shapes_they_name() {
+        // In B2a/S a cell is born beside two neighbours touching each other, so a domino leaves one
+        // domino above it and one below.
+        assert_eq!(run("B2a/S", &[(0, 0), (1, 0)], 1), [(0, -1), (1, -1), (0, 1), (1, 1)]);
+        // The cells of a domino see each other as 1e, two cells a knight's move apart as 1c.
+        assert_eq!(run("B/S1e", &[(0, 0), (1, 0)], 1), [(0, 0), (1, 0)]);
+        assert_eq!(run("B/S1e", &[(0, 0), (1, 1)], 1), []);
+    }
+
+    #[test]
+    fn tlife_differs_from_life_by_its_letters() {
+        // tlife, B3/S2-i34q, drops the 2i survival of a blinker's middle cell, so the blinker dies.
+        let blinker = [(0, 0), (1, 0), (2, 0)];
+        assert_eq!(run("B3/S23", &blinker, 2), blinker);
+        assert_eq!(run("B3/S2-i34q", &blinker, 1), [(1, -1), (1, 1)]);
+        assert_eq!(run("B3/S2-i34q", &blinker, 2), []);
+        // Every cell of a block has three neighbours in the 3a shape, so the block survives both.
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        assert_eq!(run("B3/S2-i34q", &block, 10), block);
+        assert_eq!(Rule::CONWAY, "B3/S2aceikn3".parse().unwrap());
+    }
+}
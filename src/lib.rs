@@ -1,23 +1,50 @@
 #![feature(vec_from_fn)]
 
+pub mod boundary;
+pub mod counting;
+pub mod cycle;
 pub mod implementations;
+pub mod isotropic;
+pub mod library;
+pub mod lif;
+pub mod macrocell;
+pub mod neighbourhood;
+pub mod pattern;
+pub mod plaintext;
 pub mod seed;
 pub mod rle;
+pub mod rule;
+pub mod table;
 
-const NEIGHBOUR_KERNEL: [(isize, isize); 8] = [
-    (-1, -1),
-    (0, -1),
-    (1, -1),
-    (-1, 0),
-    (1, 0),
-    (-1, 1),
-    (0, 1),
-    (1, 1),
-];
-
-/// Wraps a signed coordinate around the grid dimensions, allowing for toroidal addressing.
-fn wrap(value: isize, max: usize) -> usize {
-    value.rem_euclid(max as isize) as usize
+use boundary::Boundary;
+use counting::{configuration, NeighbourCounter};
+use rule::Rule;
+
+/// A finite grid of cells, stored row-major.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub cells: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Grid {
+    /// Creates an empty (all dead) grid.
+    pub fn new(width: usize, height: usize) -> Self {
+        Grid { cells: vec![0; width * height], width, height }
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell != 0).count()
+    }
+}
+
+/// Everything besides the cells themselves that determines how a grid evolves.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub rule: Rule,
+    pub boundary: Boundary,
 }
 
 /// Converts 2D coordinates to a 1D index in the grid buffer.
@@ -25,25 +52,44 @@ fn idx(x: usize, y: usize, width: usize) -> usize {
     y * width + x
 }
 
-/// Counts the number of alive neighbours for the cell at ([x], [y]) in the given [grid].
-fn neighbor_count(grid: &[u8], x: usize, y: usize, width: usize, height: usize) -> u8 {
-    let x = x as isize;
-    let y = y as isize;
-
-    NEIGHBOUR_KERNEL
-        .iter()
-        .rfold(0u8, |acc, (dx, dy)| {
-            let cell = grid[idx(wrap(x + dx, width), wrap(y + dy, height), width)];
-            debug_assert!(cell == 0 || cell == 1);
-            acc + cell
-        })
+/// Returns the next state of a cell under [rule], given its current state and whether the rule
+/// would have it alive, were it dead or alive. Refractory cells age regardless of their neighbours.
+fn advance_cell(rule: &Rule, current: u8, alive: bool) -> u8 {
+    let age = |state: u8| if state >= rule.states() - 1 { 0 } else { state + 1 };
+    match current {
+        0 => alive as u8,
+        1 if alive => 1,
+        _ => age(current),
+    }
 }
 
-/// Returns the next state of a cell given its current state and the number of alive neighbours.
-fn advance_cell(current: u8, neighbor_count: u8) -> u8 {
-    match (current, neighbor_count) {
-        (1, 2) | (1, 3) | (0, 3) => 1,
-        (1, _) | (0, _) => 0,
-        _ => unreachable!(),
+/// Returns the next state of the cell at ([x], [y]) in the given [grid] under [config], counting
+/// neighbours with [counter]. Rule tables look at the neighbours' states directly instead.
+fn next_cell(
+    config: &Config,
+    counter: &NeighbourCounter,
+    grid: &[u8],
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> u8 {
+    let current = grid[idx(x, y, width)];
+    let rule = &config.rule;
+    if let Some(table) = rule.table() {
+        let (x, y) = (x as isize, y as isize);
+        let boundary = config.boundary;
+        return table.next_state(|dx, dy| {
+            boundary.resolve(x + dx, y + dy, width, height).map_or(0, |(x, y)| grid[idx(x, y, width)])
+        });
     }
+    let alive = current <= 1
+        && match rule.transitions() {
+            Some(transitions) => transitions.contains(configuration(grid, x, y, width, height, config.boundary)),
+            None => {
+                let n = counter.count(config, grid, x, y, width, height);
+                if current == 0 { rule.births(n) } else { rule.survives(n) }
+            }
+        };
+    advance_cell(rule, current, alive)
 }
@@ -0,0 +1,164 @@
+//! A catalogue of well-known Game of Life patterns, embedded as RLE so that they can be seeded by
+//! name without a file.
+
+use crate::pattern::Pattern;
+use crate::rle;
+
+/// A pattern in the catalogue.
+#[derive(Debug)]
+pub struct Entry {
+    /// The name it is looked up by, in lower case with words joined by hyphens.
+    pub name: &'static str,
+    /// What it is, in a few words.
+    pub description: &'static str,
+    rle: &'static str,
+}
+
+impl Entry {
+    /// The pattern itself, decoded from its RLE.
+    pub fn pattern(&self) -> Pattern {
+        rle::parse(self.rle).expect("The built-in patterns are valid")
+    }
+}
+
+/// Every pattern in the catalogue, still lifes and oscillators first, then spaceships,
+/// methuselahs, guns and rakes.
+pub static PATTERNS: &[Entry] = &[
+    Entry {
+        name: "block",
+        description: "The most common still life",
+        rle: "#N Block\nx = 2, y = 2, rule = B3/S23\n2o$2o!",
+    },
+    Entry {
+        name: "blinker",
+        description: "The most common oscillator, period 2",
+        rle: "#N Blinker\n#O John Conway\nx = 3, y = 1, rule = B3/S23\n3o!",
+    },
+    Entry {
+        name: "toad",
+        description: "Period 2 oscillator",
+        rle: "#N Toad\n#O Simon Norton\nx = 4, y = 2, rule = B3/S23\nb3o$3o!",
+    },
+    Entry {
+        name: "beacon",
+        description: "Period 2 oscillator made of two blocks",
+        rle: "#N Beacon\n#O John Conway\nx = 4, y = 4, rule = B3/S23\n2o$o$3bo$2b2o!",
+    },
+    Entry {
+        name: "pulsar",
+        description: "Period 3 oscillator",
+        rle: "#N Pulsar\n#O John Conway\nx = 13, y = 13, rule = B3/S23\n\
+              2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$\n\
+              o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    Entry {
+        name: "pentadecathlon",
+        description: "Period 15 oscillator",
+        rle: "#N Pentadecathlon\n#O John Conway\nx = 10, y = 3, rule = B3/S23\n2bo4bo$2ob4ob2o$2bo4bo!",
+    },
+    Entry {
+        name: "glider",
+        description: "The smallest spaceship, moving diagonally at c/4",
+        rle: "#N Glider\n#O Richard K. Guy\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!",
+    },
+    Entry {
+        name: "lwss",
+        description: "Lightweight spaceship, moving orthogonally at c/2",
+        rle: "#N Lightweight spaceship\n#O John Conway\nx = 5, y = 4, rule = B3/S23\nbo2bo$o$o3bo$4o!",
+    },
+    Entry {
+        name: "mwss",
+        description: "Middleweight spaceship, moving orthogonally at c/2",
+        rle: "#N Middleweight spaceship\n#O John Conway\nx = 6, y = 5, rule = B3/S23\n3bo$bo3bo$o$o4bo$5o!",
+    },
+    Entry {
+        name: "hwss",
+        description: "Heavyweight spaceship, moving orthogonally at c/2",
+        rle: "#N Heavyweight spaceship\n#O John Conway\nx = 7, y = 5, rule = B3/S23\n3b2o$bo4bo$o$o5bo$6o!",
+    },
+    Entry {
+        name: "copperhead",
+        description: "Spaceship moving orthogonally at c/10",
+        rle: "#N Copperhead\n#O zdr\nx = 8, y = 12, rule = B3/S23\n\
+              b2o2b2o$3b2o$3b2o$obo2bobo$o6bo2$o6bo$b2o2b2o$2b4o2$3b2o$3b2o!",
+    },
+    Entry {
+        name: "r-pentomino",
+        description: "Methuselah that stabilises after 1103 generations",
+        rle: "#N R-pentomino\n#O John Conway\nx = 3, y = 3, rule = B3/S23\nb2o$2o$bo!",
+    },
+    Entry {
+        name: "b-heptomino",
+        description: "Methuselah that stabilises after 148 generations",
+        rle: "#N B-heptomino\nx = 4, y = 3, rule = B3/S23\nob2o$3o$bo!",
+    },
+    Entry {
+        name: "pi-heptomino",
+        description: "Methuselah that stabilises after 173 generations",
+        rle: "#N Pi-heptomino\nx = 3, y = 3, rule = B3/S23\n3o$obo$obo!",
+    },
+    Entry {
+        name: "acorn",
+        description: "Methuselah that stabilises after 5206 generations",
+        rle: "#N Acorn\n#O Charles Corderman\nx = 7, y = 3, rule = B3/S23\nbo$3bo$2o2b3o!",
+    },
+    Entry {
+        name: "diehard",
+        description: "Methuselah that vanishes after 130 generations",
+        rle: "#N Diehard\nx = 8, y = 3, rule = B3/S23\n6bo$2o$bo3b3o!",
+    },
+    Entry {
+        name: "infinite-growth",
+        description: "The smallest pattern in a 5x5 box that grows forever",
+        rle: "#N 5x5 infinite growth\n#O Paul Callahan\nx = 5, y = 5, rule = B3/S23\n3obo$o$3b2o$b2obo$obobo!",
+    },
+    Entry {
+        name: "gosper-gun",
+        description: "The first known gun, firing a glider every 30 generations",
+        rle: "#N Gosper glider gun\n#O Bill Gosper\nx = 36, y = 9, rule = B3/S23\n\
+              24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$\n\
+              10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    Entry {
+        name: "simkin-gun",
+        description: "Gun firing a glider every 120 generations",
+        rle: "#N Simkin glider gun\n#O Michael Simkin\nx = 33, y = 21, rule = B3/S23\n\
+              2o5b2o$2o5b2o2$4b2o$4b2o5$22b2ob2o$21bo5bo$21bo6bo2b2o$21b3o3bo3b2o$26bo4$\n\
+              20b2o$20bo$21b3o$23bo!",
+    },
+    Entry {
+        name: "space-rake",
+        description: "Flies at c/2 and fires a glider every 20 generations",
+        rle: "#N Space rake\nx = 22, y = 19, rule = B3/S23\n\
+              11b2o5b4o$9b2ob2o3bo3bo$9b4o8bo$10b2o5bo2bo2$8bo$7b2o8b2o$6bo9bo2bo$7b5o4bo2bo$\n\
+              8b4o3b2ob2o$11bo4b2o4$18b4o$o2bo13bo3bo$4bo16bo$o3bo12bo2bo$b4o!",
+    },
+];
+
+/// The pattern in the catalogue called [name], ignoring case and treating spaces and underscores
+/// as hyphens.
+pub fn get(name: &str) -> Option<&'static Entry> {
+    let name = name.trim().to_ascii_lowercase().replace([' ', '_'], "-");
+    PATTERNS.iter().find(|entry| entry.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implementations::hashlife::HashLife;
+    use crate::rule::Rule;
+
+    #[test]
+    fn guns_and_rakes_fire_a_glider_every_period() {
+        for (name, period) in [("gosper-gun", 30), ("simkin-gun", 120), ("space-rake", 20)] {
+            let pattern = get(name).unwrap().pattern();
+            let mut universe = HashLife::from_cells(&pattern.cells, pattern.width, pattern.height, Rule::CONWAY);
+
+            // Give the first gliders time to leave before counting.
+            universe.advance_by(10 * period).unwrap();
+            let population = universe.population();
+            universe.advance_by(10 * period).unwrap();
+            assert_eq!(universe.population(), population + 10 * 5, "{name}");
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Life 1.05 and 1.06 files (`.lif`, `.life`). Life 1.05 holds `#D` description lines, the rule as
+//! `#N` for Conway's Game of Life or `#R` followed by a survival-first rulestring, and blocks of
+//! rows of `.` and `*`, each starting at the position given by a `#P x y` line. Life 1.06 is just a
+//! list of the coordinates of live cells, one `x y` pair per line.
+
+use crate::neighbourhood::Neighbourhood;
+use crate::pattern::{Pattern, ReadError};
+use crate::rule::Rule;
+use std::fmt::Write;
+
+/// The first line of a Life 1.05 file.
+pub const HEADER_105: &str = "#Life 1.05";
+
+/// The first line of a Life 1.06 file.
+pub const HEADER_106: &str = "#Life 1.06";
+
+/// The widest block [encode_105] writes, as older readers expect.
+const LINE_LENGTH: usize = 80;
+
+/// Parses the text of a Life 1.05 file. `#D Name:` and `#D Author:` lines fill in the pattern's
+/// name and author, other `#D` (or `#C`) lines become its comments, and rows before the first `#P`
+/// line start at the origin.
+pub fn parse_105(text: &str) -> Result<Pattern, ReadError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+    if !lines.next().is_some_and(|(_, line)| line.starts_with(HEADER_105)) {
+        return Err(ReadError::MissingHeader { expected: HEADER_105 });
+    }
+
+    let (mut name, mut author, mut comments, mut rule) = (None, None, Vec::new(), None);
+    let mut cells = Vec::new();
+    let (mut origin, mut y) = ((0, 0), 0);
+    for (number, line) in lines {
+        if let Some(comment) = line.strip_prefix('#') {
+            let (kind, rest) = comment.split_at(comment.chars().next().map_or(0, char::len_utf8));
+            let rest = rest.trim();
+            match kind {
+                "D" | "C" => {
+                    if let Some(value) = rest.strip_prefix("Name:") {
+                        name = Some(value.trim().to_string());
+                    } else if let Some(value) = rest.strip_prefix("Author:") {
+                        author = Some(value.trim().to_string());
+                    } else {
+                        comments.push(rest.to_string());
+                    }
+                }
+                "N" => rule = Some(Rule::CONWAY),
+                "R" => rule = Some(rest.parse().map_err(|message| ReadError::InvalidRule { line: number, message })?),
+                "P" => {
+                    origin = coordinates(rest).ok_or(ReadError::InvalidCoordinates { line: number })?;
+                    y = 0;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        for (x, ch) in line.chars().enumerate() {
+            match ch {
+                '.' => {}
+                '*' | 'O' | 'o' => cells.push((origin.0 + x as i64, origin.1 + y, 1)),
+                found => return Err(ReadError::UnexpectedCharacter { line: number, column: x + 1, found }),
+            }
+        }
+        y += 1;
+    }
+
+    let mut pattern = from_cells(&cells)?;
+    (pattern.name, pattern.author, pattern.comments, pattern.rule) = (name, author, comments, rule);
+    Ok(pattern)
+}
+
+/// Parses the text of a Life 1.06 file. Any `#` lines after the header become the pattern's
+/// comments.
+pub fn parse_106(text: &str) -> Result<Pattern, ReadError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+    if !lines.next().is_some_and(|(_, line)| line.starts_with(HEADER_106)) {
+        return Err(ReadError::MissingHeader { expected: HEADER_106 });
+    }
+
+    let (mut comments, mut cells) = (Vec::new(), Vec::new());
+    for (number, line) in lines.filter(|(_, line)| !line.is_empty()) {
+        match line.strip_prefix('#') {
+            Some(comment) => comments.push(comment.get(1..).unwrap_or("").trim().to_string()),
+            None => {
+                let (x, y) = coordinates(line).ok_or(ReadError::InvalidCoordinates { line: number })?;
+                cells.push((x, y, 1));
+            }
+        }
+    }
+
+    let mut pattern = from_cells(&cells)?;
+    pattern.comments = comments;
+    Ok(pattern)
+}
+
+/// Encodes [pattern] as Life 1.05, with its name, author and comments as `#D` lines, the first two
+/// labelled `Name:` and `Author:` as in plaintext files. Cells in any state but dead are written
+/// alive, and the rule is only written if it is a life-like rule over the Moore neighbourhood, the
+/// only kind the format can describe. Patterns wider than 80 cells are split into several blocks.
+pub fn encode_105(pattern: &Pattern) -> String {
+    let mut out = format!("{HEADER_105}\n");
+    if let Some(name) = &pattern.name {
+        writeln!(out, "#D Name: {name}").unwrap();
+    }
+    if let Some(author) = &pattern.author {
+        writeln!(out, "#D Author: {author}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(out, "#D {comment}").unwrap();
+    }
+    match pattern.rule {
+        Some(rule) if rule == Rule::CONWAY => writeln!(out, "#N").unwrap(),
+        Some(rule) if rule.is_life_like() && rule.is_totalistic() && rule.neighbourhood() == Neighbourhood::Moore => {
+            let rule = rule.to_string();
+            let (birth, survival) = rule.trim_start_matches('B').split_once("/S").unwrap();
+            writeln!(out, "#R {survival}/{birth}").unwrap();
+        }
+        _ => {}
+    }
+
+    let (left, top) = origin(pattern);
+    for start in (0..pattern.width).step_by(LINE_LENGTH) {
+        let end = (start + LINE_LENGTH).min(pattern.width);
+        let row = |y: usize| &pattern.cells[y * pattern.width + start..y * pattern.width + end];
+        let occupied: Vec<usize> = (0..pattern.height).filter(|&y| row(y).iter().any(|&cell| cell != 0)).collect();
+        let (Some(&first), Some(&last)) = (occupied.first(), occupied.last()) else {
+            continue;
+        };
+
+        writeln!(out, "#P {} {}", left + start as i64, top + first as i64).unwrap();
+        for y in first..=last {
+            let cells = row(y);
+            let length = cells.iter().rposition(|&cell| cell != 0).map_or(1, |x| x + 1);
+            out.extend(cells[..length].iter().map(|&cell| if cell == 0 { '.' } else { '*' }));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Encodes [pattern] as Life 1.06. The format has no room for metadata or states, so only the
+/// positions of cells in any state but dead are written.
+pub fn encode_106(pattern: &Pattern) -> String {
+    let mut out = format!("{HEADER_106}\n");
+    let (left, top) = origin(pattern);
+    for y in 0..pattern.height {
+        for x in (0..pattern.width).filter(|&x| pattern.get(x, y) != 0) {
+            writeln!(out, "{} {}", left + x as i64, top + y as i64).unwrap();
+        }
+    }
+    out
+}
+
+/// Parses a pair of coordinates separated by whitespace.
+fn coordinates(text: &str) -> Option<(i64, i64)> {
+    let mut values = text.split_whitespace().map(str::parse);
+    match (values.next(), values.next(), values.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// The pattern holding [cells], see [Pattern::from_cells].
+fn from_cells(cells: &[(i64, i64, u8)]) -> Result<Pattern, ReadError> {
+    Pattern::from_cells(cells).ok_or(ReadError::TooLarge)
+}
+
+/// Where the top-left corner of [pattern] lies relative to the origin: its offset, or else placed
+/// so that the origin is in its middle.
+fn origin(pattern: &Pattern) -> (i64, i64) {
+    pattern.offset.unwrap_or((-(pattern.width as i64 / 2), -(pattern.height as i64 / 2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A trimmed pattern wider than a Life 1.05 block, with cells along each edge.
+    fn wide() -> Pattern {
+        let mut pattern = Pattern::new(170, 4);
+        for (x, y) in [(0, 1), (40, 0), (81, 3), (120, 2), (169, 1), (169, 2)] {
+            pattern.cells[y * 170 + x] = 1;
+        }
+        pattern.offset = Some((-60, 7));
+        pattern
+    }
+
+    #[test]
+    fn round_trips_life_105() {
+        let mut pattern = wide();
+        pattern.name = Some("Wide".to_string());
+        pattern.author = Some("Nobody in particular".to_string());
+        pattern.comments = vec!["Scattered cells".to_string()];
+        for rule in ["B3/S23", "B36/S23"] {
+            pattern.rule = Some(rule.parse().unwrap());
+            let text = encode_105(&pattern);
+            assert!(text.lines().all(|line| line.len() <= LINE_LENGTH));
+            assert_eq!(parse_105(&text).unwrap(), pattern);
+        }
+    }
+
+    #[test]
+    fn round_trips_life_106() {
+        let pattern = wide();
+        assert_eq!(parse_106(&encode_106(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        assert!(matches!(
+            parse_105("#Life 1.05\n#P 0 0\n.*\n*x"),
+            Err(ReadError::UnexpectedCharacter { line: 4, column: 2, found: 'x' })
+        ));
+        assert!(matches!(parse_106("#Life 1.06\n0 0\n1"), Err(ReadError::InvalidCoordinates { line: 3 })));
+        assert!(matches!(parse_106("0 0\n"), Err(ReadError::MissingHeader { .. })));
+    }
+}
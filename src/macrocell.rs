@@ -0,0 +1,387 @@
+//! Golly's macrocell format (`.mc`), which stores a pattern as a quadtree with identical squares
+//! shared, so that huge but repetitive patterns stay small. After a `[M2]` header and `#` comment
+//! lines, of which `#R` gives the rule and `#G` the generation, each line defines a node, numbered
+//! from 1. A node is either an 8x8 square of two-state cells, written as rows of `.` and `*` each
+//! ended by `$`, or `level nw ne sw se`, naming four earlier nodes as its quadrants, or 0 for an
+//! empty one. Patterns with more than two states have no 8x8 squares, and instead give the states of
+//! single cells as the quadrants of level 1 nodes. The last node is the root, centred on the origin.
+
+use crate::pattern::{Pattern, ReadError};
+use crate::rule::Rule;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The first line of a macrocell file, which may be followed by the name of the program that
+/// wrote it.
+pub const HEADER: &str = "[M2]";
+
+/// The deepest node allowed, so that every cell has coordinates that fit in an `i64`.
+const MAX_LEVEL: u8 = 62;
+
+/// One node of a [Macrocell].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Node {
+    /// An 8x8 square of two-state cells, one byte per row, with bit `x` set if column `x` is alive.
+    Square([u8; 8]),
+    /// A square `2^level` cells wide made of four quadrants: north-west, north-east, south-west
+    /// and south-east. At level 1 these are the states of single cells, and above that the numbers
+    /// of earlier nodes one level down, or 0 where empty.
+    Branch { level: u8, children: [u32; 4] },
+}
+
+impl Node {
+    pub fn level(&self) -> u8 {
+        match self {
+            Node::Square(_) => 3,
+            Node::Branch { level, .. } => *level,
+        }
+    }
+}
+
+/// A pattern as a quadtree, as stored in a macrocell file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Macrocell {
+    /// The nodes in the order they are numbered, from 1, so that each comes after its quadrants.
+    /// The last is the root. There are none if the pattern is empty.
+    pub nodes: Vec<Node>,
+    pub rule: Option<Rule>,
+    /// The generation the pattern was saved at.
+    pub generation: u64,
+    pub comments: Vec<String>,
+}
+
+impl Macrocell {
+    /// Builds the quadtree of [pattern], placed at its offset or else centred on the origin, which
+    /// must lie within `2^61` cells of it. Patterns whose rule or cells have more than two states
+    /// are stored with multi-state leaves.
+    pub fn from_pattern(pattern: &Pattern) -> Self {
+        let multi_state = pattern.rule.is_some_and(|rule| rule.states() > 2) || pattern.cells.iter().any(|&cell| cell > 1);
+        let (left, top) = pattern.offset.unwrap_or((-(pattern.width as i64 / 2), -(pattern.height as i64 / 2)));
+        let (right, bottom) = (left + pattern.width as i64, top + pattern.height as i64);
+        let mut level = if multi_state { 1 } else { 3 };
+        let fits = |level: u8| {
+            [left, top].iter().all(|&start| start >= -(1 << (level - 1)))
+                && [right, bottom].iter().all(|&end| end <= 1 << (level - 1))
+        };
+        while level < MAX_LEVEL && !fits(level) {
+            level += 1;
+        }
+
+        let mut builder = Builder { pattern, left, top, multi_state, nodes: Vec::new(), numbers: HashMap::new() };
+        let half = 1i64 << (level - 1);
+        builder.build(level, -half, -half);
+        Macrocell { nodes: builder.nodes, rule: pattern.rule, generation: 0, comments: pattern.comments.clone() }
+    }
+
+    /// The root node, if the pattern is not empty.
+    pub fn root(&self) -> Option<&Node> {
+        self.nodes.last()
+    }
+
+    /// Draws the quadtree into a pattern cut down to its live cells, with its offset from the
+    /// origin, or [None] if they span too large an area to store.
+    pub fn to_pattern(&self) -> Option<Pattern> {
+        let empty = Pattern { rule: self.rule, comments: self.comments.clone(), ..Pattern::default() };
+        let Some(root) = self.root() else {
+            return Some(empty);
+        };
+        let half = 1i64 << (root.level() - 1);
+        let Some([left, top, right, bottom]) = self.bounds().pop().flatten().map(|bounds| bounds.map(|edge| edge - half)) else {
+            return Some(empty);
+        };
+
+        let mut pattern = Pattern::from_cells(&[(left, top, 0), (right, bottom, 0)])?;
+        self.visit(self.nodes.len() as u32, -half, -half, &mut |x, y, state| {
+            pattern.cells[(y - top) as usize * pattern.width + (x - left) as usize] = state;
+        });
+        (pattern.rule, pattern.comments) = (self.rule, self.comments.clone());
+        Some(pattern)
+    }
+
+    /// The bounding box `[left, top, right, bottom]` of the live cells, inclusive and relative to
+    /// the origin, or [None] if there are none. Found without visiting the cells themselves.
+    pub fn bounding_box(&self) -> Option<[i64; 4]> {
+        let half = 1i64 << (self.root()?.level() - 1);
+        self.bounds().pop().flatten().map(|bounds| bounds.map(|edge| edge - half))
+    }
+
+    /// The bounding box `[left, top, right, bottom]` of the live cells of each node, inclusive and
+    /// relative to the node's top-left corner, or [None] for empty nodes. Each is found from those
+    /// of its quadrants, so this never visits the cells themselves.
+    fn bounds(&self) -> Vec<Option<[i64; 4]>> {
+        let mut bounds: Vec<Option<[i64; 4]>> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let boxes: Vec<[i64; 4]> = match *node {
+                Node::Square(rows) => {
+                    let live = (0..64).filter(|&i| rows[i as usize / 8] & 1 << (i % 8) != 0);
+                    live.map(|i| [i % 8, i / 8, i % 8, i / 8]).collect()
+                }
+                Node::Branch { level: 1, children } => {
+                    let live = (0..4).filter(|&i| children[i as usize] != 0);
+                    live.map(|i| [i % 2, i / 2, i % 2, i / 2]).collect()
+                }
+                Node::Branch { level, children } => {
+                    let half = 1i64 << (level - 1);
+                    (0..4)
+                        .filter(|&i| children[i] != 0)
+                        .filter_map(|i| {
+                            let (dx, dy) = (half * (i as i64 % 2), half * (i as i64 / 2));
+                            bounds[children[i] as usize - 1].map(|[l, t, r, b]| [l + dx, t + dy, r + dx, b + dy])
+                        })
+                        .collect()
+                }
+            };
+            bounds.push(boxes.into_iter().reduce(|[l, t, r, b], [l2, t2, r2, b2]| [l.min(l2), t.min(t2), r.max(r2), b.max(b2)]));
+        }
+        bounds
+    }
+
+    /// Calls [f] with the position and state of each live cell in the node numbered [number], whose
+    /// top-left corner is at ([x], [y]).
+    fn visit(&self, number: u32, x: i64, y: i64, f: &mut impl FnMut(i64, i64, u8)) {
+        if number == 0 {
+            return;
+        }
+        match self.nodes[number as usize - 1] {
+            Node::Square(rows) => {
+                for (dy, row) in rows.into_iter().enumerate() {
+                    for dx in (0..8).filter(|dx| row & 1 << dx != 0) {
+                        f(x + dx, y + dy as i64, 1);
+                    }
+                }
+            }
+            Node::Branch { level: 1, children } => {
+                for (i, state) in children.into_iter().enumerate().filter(|&(_, state)| state != 0) {
+                    f(x + i as i64 % 2, y + i as i64 / 2, state as u8);
+                }
+            }
+            Node::Branch { level, children } => {
+                let half = 1i64 << (level - 1);
+                for (i, child) in children.into_iter().enumerate() {
+                    self.visit(child, x + half * (i as i64 % 2), y + half * (i as i64 / 2), f);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the nodes of a [Macrocell] from a [Pattern], sharing identical ones.
+struct Builder<'a> {
+    pattern: &'a Pattern,
+    /// Where the pattern's top-left corner lies.
+    left: i64,
+    top: i64,
+    multi_state: bool,
+    nodes: Vec<Node>,
+    numbers: HashMap<Node, u32>,
+}
+
+impl Builder<'_> {
+    /// The state of the cell at ([x], [y]) relative to the origin.
+    fn get(&self, x: i64, y: i64) -> u8 {
+        let (x, y) = (x - self.left, y - self.top);
+        let inside = (0..self.pattern.width as i64).contains(&x) && (0..self.pattern.height as i64).contains(&y);
+        if inside { self.pattern.get(x as usize, y as usize) } else { 0 }
+    }
+
+    /// The number of the node of [level] whose top-left corner is at ([x], [y]), or 0 if it is
+    /// empty.
+    fn build(&mut self, level: u8, x: i64, y: i64) -> u32 {
+        let size = 1i64 << level;
+        let (right, bottom) = (self.left + self.pattern.width as i64, self.top + self.pattern.height as i64);
+        if x + size <= self.left || y + size <= self.top || x >= right || y >= bottom {
+            return 0;
+        }
+
+        let node = if level == 3 && !self.multi_state {
+            let rows = std::array::from_fn(|dy| {
+                (0..8).filter(|&dx| self.get(x + dx, y + dy as i64) != 0).fold(0, |row, dx| row | 1 << dx)
+            });
+            Node::Square(rows)
+        } else if level == 1 {
+            Node::Branch { level, children: [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| self.get(x + dx, y + dy) as u32) }
+        } else {
+            let half = size / 2;
+            let children = [(0, 0), (half, 0), (0, half), (half, half)].map(|(dx, dy)| self.build(level - 1, x + dx, y + dy));
+            Node::Branch { level, children }
+        };
+
+        match node {
+            Node::Square([0, 0, 0, 0, 0, 0, 0, 0]) | Node::Branch { children: [0, 0, 0, 0], .. } => 0,
+            node => *self.numbers.entry(node).or_insert_with(|| {
+                self.nodes.push(node);
+                self.nodes.len() as u32
+            }),
+        }
+    }
+}
+
+/// Parses the text of a macrocell file. `#C` (or `#D`) lines become the pattern's comments, and
+/// other comment lines Golly writes, such as `#FRAMES`, are skipped.
+pub fn parse(text: &str) -> Result<Macrocell, ReadError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+    if !lines.next().is_some_and(|(_, line)| line.starts_with(HEADER)) {
+        return Err(ReadError::MissingHeader { expected: HEADER });
+    }
+
+    let mut macrocell = Macrocell::default();
+    for (number, line) in lines.filter(|(_, line)| !line.is_empty()) {
+        let invalid = |message: String| ReadError::InvalidNode { line: number, message };
+        if let Some(comment) = line.strip_prefix('#') {
+            let (kind, rest) = comment.split_at(comment.chars().next().map_or(0, char::len_utf8));
+            let rest = rest.trim();
+            match kind {
+                "R" => macrocell.rule = Some(rest.parse().map_err(|message| ReadError::InvalidRule { line: number, message })?),
+                "G" => {
+                    macrocell.generation = rest.parse().map_err(|_| ReadError::InvalidHeader {
+                        line: number,
+                        message: format!("invalid generation '{rest}'"),
+                    })?
+                }
+                "C" | "D" => macrocell.comments.push(rest.to_string()),
+                _ => {}
+            }
+            continue;
+        }
+
+        let node = if line.starts_with(['.', '*', '$']) {
+            parse_square(line, number)?
+        } else {
+            let values: Vec<u32> = line
+                .split_whitespace()
+                .map(|value| value.parse().map_err(|_| invalid(format!("invalid number '{value}'"))))
+                .collect::<Result<_, _>>()?;
+            let [level, nw, ne, sw, se] = values[..] else {
+                return Err(invalid("expected a level and four quadrants".to_string()));
+            };
+            let children = [nw, ne, sw, se];
+            match level {
+                0 => return Err(invalid("nodes start at level 1".to_string())),
+                1 => {
+                    let states = macrocell.rule.map_or(256, |rule| rule.states() as u32);
+                    if let Some(&state) = children.iter().find(|&&state| state >= states) {
+                        return Err(invalid(format!("invalid state {state}")));
+                    }
+                }
+                level if level > MAX_LEVEL as u32 => return Err(invalid(format!("level {level} is too deep"))),
+                level => {
+                    for child in children.into_iter().filter(|&child| child != 0) {
+                        let below = macrocell.nodes.get(child as usize - 1).map(Node::level);
+                        if below != Some(level as u8 - 1) {
+                            return Err(invalid(format!("quadrant {child} is not an earlier node of level {}", level - 1)));
+                        }
+                    }
+                }
+            }
+            Node::Branch { level: level as u8, children }
+        };
+        macrocell.nodes.push(node);
+    }
+    Ok(macrocell)
+}
+
+/// Parses an 8x8 square of two-state cells on line [number].
+fn parse_square(line: &str, number: usize) -> Result<Node, ReadError> {
+    let mut rows = [0u8; 8];
+    let (mut x, mut y) = (0, 0);
+    for (column, ch) in line.chars().enumerate().map(|(i, ch)| (i + 1, ch)) {
+        match ch {
+            '$' => (x, y) = (0, y + 1),
+            '.' | '*' if x >= 8 || y >= 8 => {
+                return Err(ReadError::InvalidNode { line: number, message: format!("column {column} lies outside the 8x8 square") });
+            }
+            '.' => x += 1,
+            '*' => {
+                rows[y] |= 1 << x;
+                x += 1;
+            }
+            found => return Err(ReadError::UnexpectedCharacter { line: number, column, found }),
+        }
+    }
+    Ok(Node::Square(rows))
+}
+
+/// Encodes [macrocell], with its rule, generation and comments as comment lines. Rows of 8x8
+/// squares stop at their last live cell, and empty rows at the bottom are left out.
+pub fn encode(macrocell: &Macrocell) -> String {
+    let mut out = format!("{HEADER} ({} {})\n", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    if let Some(rule) = macrocell.rule {
+        writeln!(out, "#R {rule}").unwrap();
+    }
+    if macrocell.generation != 0 {
+        writeln!(out, "#G {}", macrocell.generation).unwrap();
+    }
+    for comment in &macrocell.comments {
+        writeln!(out, "#C {comment}").unwrap();
+    }
+
+    for node in &macrocell.nodes {
+        match node {
+            Node::Square(rows) => {
+                let used = rows.iter().rposition(|&row| row != 0).map_or(0, |y| y + 1);
+                for &row in &rows[..used] {
+                    let length = u8::BITS - row.leading_zeros();
+                    out.extend((0..length).map(|x| if row & 1 << x != 0 { '*' } else { '.' }));
+                    out.push('$');
+                }
+            }
+            Node::Branch { level, children: [nw, ne, sw, se] } => write!(out, "{level} {nw} {ne} {sw} {se}").unwrap(),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pattern of [width] by [height] cells with [cells] set, placed at [offset].
+    fn pattern(width: usize, height: usize, offset: (i64, i64), cells: &[(usize, usize, u8)]) -> Pattern {
+        let mut pattern = Pattern::new(width, height);
+        for &(x, y, state) in cells {
+            pattern.cells[y * width + x] = state;
+        }
+        pattern.offset = Some(offset);
+        pattern
+    }
+
+    #[test]
+    fn round_trips_two_state_patterns() {
+        // A glider and a block far enough apart to need several levels of nodes.
+        let mut pattern = pattern(
+            40,
+            23,
+            (-30, 5),
+            &[(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1), (38, 21, 1), (39, 21, 1), (38, 22, 1), (39, 22, 1)],
+        );
+        pattern.rule = Some("B36/S23".parse().unwrap());
+        pattern.comments = vec!["A glider and a block".to_string()];
+
+        let mut macrocell = Macrocell::from_pattern(&pattern);
+        macrocell.generation = 1234;
+        let read = parse(&encode(&macrocell)).unwrap();
+        assert_eq!(read, macrocell);
+        assert_eq!(read.to_pattern(), Some(pattern));
+    }
+
+    #[test]
+    fn round_trips_multi_state_patterns() {
+        let mut pattern = pattern(3, 2, (-1, -1), &[(0, 0, 1), (2, 0, 2), (1, 1, 2)]);
+        pattern.rule = Some("B2/S/C3".parse().unwrap());
+
+        let macrocell = Macrocell::from_pattern(&pattern);
+        assert!(macrocell.nodes.iter().all(|node| matches!(node, Node::Branch { .. })));
+        let read = parse(&encode(&macrocell)).unwrap();
+        assert_eq!(read, macrocell);
+        assert_eq!(read.to_pattern(), Some(pattern));
+    }
+
+    #[test]
+    fn round_trips_empty_patterns() {
+        let macrocell = Macrocell::from_pattern(&Pattern::new(5, 5));
+        let read = parse(&encode(&macrocell)).unwrap();
+        assert_eq!(read, macrocell);
+        assert_eq!(read.to_pattern().map(|pattern| pattern.population()), Some(0));
+    }
+}
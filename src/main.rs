@@ -1,51 +1,91 @@
 mod demonstrations;
 
-use clap::{Parser, ValueEnum};
-use minifb::{Key, Window, WindowOptions};
-use minifb_fonts::font6x8;
-use rust_102::implementations::parallel::step_parallel;
-use rust_102::implementations::pool::{initialise_pool, step_pool};
-use rust_102::implementations::serial::step_serial;
-use rust_102::implementations::workers::step_workers;
-use rust_102::rle::decode_rle_into_centered;
-use rust_102::seed::seed_gosper;
-use std::mem::swap;
-use std::path::PathBuf;
+use clap::builder::RangedU64ValueParser;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use minifb_fonts::{font6x8, FbFontRenderer};
+use rust_102::boundary::Boundary;
+use rust_102::cycle::{self, Cycle, History};
+use rust_102::implementations::activity::{ActivityStats, DEFAULT_TILE_SIZE};
+use rust_102::implementations::hashlife::{HashLife, HashLifeStepper, MAX_STEP_LOG};
+use rust_102::implementations::{GridView, Mode, Stepper, Tuning};
+use rust_102::library::{self, Entry};
+use rust_102::neighbourhood::Neighbourhood;
+use rust_102::macrocell::{self, Macrocell};
+use rust_102::pattern::{self, Anchor, Flip, Format, Oversize, Pattern, Placement, ReadError, MAX_CELLS};
+use rust_102::rle;
+use rust_102::rule::Rule;
+use rust_102::seed::{parse_seed, seed_gosper, Soup, Symmetry};
+use rust_102::{Config, Grid};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
 
 const ALIVE_COLOUR: u32 = 0xFFFFFF;
 const DEAD_COLOUR: u32 = 0x000000;
+/// The colour of the first refractory state of a Generations rule; later states fade to black.
+const DYING_COLOUR: u32 = 0xFF8000;
 const TEXT_COLOUR: u32 = 0x00FF00;
 const TEXT_HEIGHT: usize = 12;
 const FPS_UPDATE_INTERVAL: f64 = 0.5;
-
-#[derive(Copy, Clone, ValueEnum, Debug)]
-#[derive(PartialEq)]
-enum Mode {
-    Serial,
-    Parallel,
-    Workers,
-    Pool,
-}
+/// How far the arrow keys pan the view, in pixels.
+const PAN_STEP: f64 = 16.0;
+/// The closest zoom, in pixels per cell, and the furthest, in cells per pixel.
+const MAX_ZOOM_IN: usize = 64;
+const MAX_ZOOM_OUT: usize = 8;
+/// The closest zoom at which grid lines are drawn, in pixels per cell.
+const GRID_LINE_ZOOM: usize = 4;
+const GRID_LINE_COLOUR: u32 = 0x303030;
+/// The target speed, in generations per second, when the throttle is turned on without `--speed`.
+const DEFAULT_SPEED: f64 = 60.0;
+const DEFAULT_CHUNK_SIZE: usize = 256;
+/// Dead cells left around the seeds of a grid sized to fit them, see [fitted_grid_size].
+const HEADLESS_MARGIN: usize = 64;
+/// The size of a soup with no `--soup-size` in a grid sized to fit the seeds.
+const DEFAULT_SOUP_SIZE: (usize, usize) = (16, 16);
+/// The size of the Gosper glider gun placed when there are no seeds.
+const GOSPER_GUN_SIZE: (usize, usize) = (36, 9);
 
 #[derive(Parser)]
 #[command(
     name = "automata",
     version = env!("CARGO_PKG_VERSION"),
 )]
-struct CLI {
+struct Cli {
+    /// Lists the patterns in the built-in library, and exits.
+    #[arg(long, exclusive = true)]
+    list_patterns: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// What to simulate, and how: the grid, the engine, the rule and the seeds. Shared by every
+/// command that runs a simulation.
+#[derive(Args)]
+struct Setup {
     /// Window size in pixels.
     #[arg(short, long, value_parser = parse_window_size, default_value = "800x600")]
     size: (usize, usize),
 
+    /// The size of the grid, in cells. Defaults to the size of the window, less the status bar, or
+    /// without a window to the seeds with 64 dead cells around them.
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_window_size)]
+    grid: Option<(usize, usize)>,
+
     /// What strategy to use for stepping the simulation.
-    #[arg(short, long)]
+    #[arg(short, long, value_enum, default_value_t = Mode::Serial)]
     mode: Mode,
 
-    /// Chunk size. Required when using the [Workers] or [Pool] mode. Ignored otherwise.
+    /// Chunk size. Required when starting in the [Workers] or [Pool] mode, and 256 when switching to
+    /// them with Tab. Ignored otherwise.
     #[arg(
         short = 'c',
         long,
@@ -53,15 +93,778 @@ struct CLI {
     )]
     chunk_size: Option<usize>,
 
-    /// An optional run-length-encoded initial state to replace the default seed.
+    /// The side length, in cells, of the tiles used to skip regions that have stopped changing.
+    #[arg(long, default_value_t = DEFAULT_TILE_SIZE, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    tile_size: usize,
+
+    /// The rule to simulate, as a rulestring such as `B36/S23`, `23/36`, the Generations
+    /// `B2/S345/C4`, the isotropic non-totalistic `B2-a/S12` or the Larger than Life
+    /// `R5,C0,M1,S34..58,B34..45,NM`, the built-in `WireWorld`, or the path of a Golly `.rule` file.
+    /// Defaults to the rule in the seed's header, or B3/S23.
+    #[arg(short, long)]
+    rule: Option<Rule>,
+
+    /// Which cells count as neighbours, overriding the rule's own: `moore`, `von-neumann`,
+    /// `hexagonal`, `moore:R` or `von-neumann:R` for range R, or a list of offsets such as
+    /// `-1,0;1,0;0,-1;0,1`.
+    #[arg(short, long, allow_hyphen_values = true)]
+    neighbourhood: Option<Neighbourhood>,
+
+    /// What lies beyond the edges of the grid.
+    #[arg(short, long, value_enum, default_value_t = Boundary::Torus)]
+    boundary: Boundary,
+
+    /// A pattern file to place into the grid instead of the default seed: RLE, plaintext
+    /// (`.cells`), Life 1.05 or 1.06 (`.lif`), or macrocell (`.mc`). May be given more than once to
+    /// combine several, each placed by the options that follow it; options given before any seed
+    /// apply to all of them.
     #[arg(long, value_name = "FILE")]
-    seed: Option<PathBuf>,
+    seed: Vec<PathBuf>,
+
+    /// A pattern from the built-in library to place into the grid, such as `glider` or
+    /// `gosper-gun`. Placed like a `--seed`, and may likewise be given more than once.
+    #[arg(long, value_name = "NAME", value_parser = parse_pattern)]
+    pattern: Vec<&'static Entry>,
+
+    /// Which point of the seed to line up with the same point of the grid. `origin` uses the offset
+    /// saved in the file, from the middle of the grid.
+    #[arg(long, value_enum)]
+    anchor: Vec<Anchor>,
+
+    /// Moves the seed right by X and down by Y cells from where its anchor puts it.
+    #[arg(long, value_name = "X,Y", value_parser = parse_offset, allow_hyphen_values = true)]
+    at: Vec<(i64, i64)>,
+
+    /// Mirrors the seed, before rotating it.
+    #[arg(long, value_enum)]
+    flip: Vec<Flip>,
+
+    /// Turns the seed clockwise by 90, 180 or 270 degrees.
+    #[arg(long, value_name = "DEGREES", value_parser = parse_rotation)]
+    rotate: Vec<u8>,
+
+    /// Repeats the seed across the whole grid, leaving GAP dead cells between copies (none if
+    /// just `--tile` is given).
+    #[arg(long, value_name = "GAP", num_args = 0..=1, require_equals = true, default_missing_value = "0")]
+    tile: Vec<usize>,
+
+    /// Crop seeds larger than the grid to fit, rather than refusing to start.
+    #[arg(long)]
+    crop: bool,
+
+    /// Starts from a random soup, generated from SEED: a number, or any other text, which is
+    /// hashed. The same seed always gives the same soup. Any `--seed`s and `--pattern`s are placed on top.
+    #[arg(long, value_name = "SEED")]
+    soup: Option<String>,
+
+    /// The size of the soup, in the middle of the grid, such as the canonical `16x16`. Fills the
+    /// window's grid if not given, or is `16x16` without a window.
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_window_size)]
+    soup_size: Option<(usize, usize)>,
+
+    /// The chance of each cell of the soup being alive, from 0 to 1.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_density)]
+    density: f64,
+
+    /// Makes the soup symmetric, as in apgsearch. `c4` and `d8` need a square soup.
+    #[arg(long, value_enum, default_value_t = Symmetry::C1)]
+    symmetry: Symmetry,
+}
+
+/// A seed to place into the grid.
+enum Seed<'a> {
+    File(&'a PathBuf),
+    Library(&'static Entry),
+}
+
+impl fmt::Display for Seed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Seed::File(path) => write!(f, "{}", path.display()),
+            Seed::Library(entry) => f.write_str(entry.name),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Opens a window and runs the simulation in it. The default when no command is given.
+    Run {
+        #[command(flatten)]
+        setup: Setup,
+
+        /// Log2 of the number of generations to advance per frame when using the [Hashlife] mode,
+        /// at most 59. Ignored otherwise.
+        #[arg(long, default_value_t = 0, value_parser = RangedU64ValueParser::<u8>::new().range(..=MAX_STEP_LOG as u64))]
+        step_log: u8,
+
+        /// The number of generations to advance per frame, of which only the last is drawn. `+` and
+        /// `-` double and halve it. Multiplied by `2^step_log` in the [Hashlife] mode.
+        #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+        steps_per_frame: u64,
+
+        /// Throttles the simulation to at most this many generations per second. `T` turns the
+        /// throttle on and off, and `[` and `]` halve and double the target.
+        #[arg(long, value_name = "GENERATIONS", value_parser = parse_speed)]
+        speed: Option<f64>,
+
+        /// Starts paused. Space pauses and resumes, and `N` advances a single generation.
+        #[arg(long)]
+        paused: bool,
+
+        /// How far in to start zoomed: `N` for N pixels per cell, or `1/N` for N cells per pixel.
+        /// The mouse wheel and Page Up and Page Down zoom in and out, and Home resets the view.
+        #[arg(short, long, value_parser = parse_zoom, default_value = "1")]
+        zoom: Zoom,
+
+        /// Draws grid lines between cells when zoomed in to 4 pixels per cell or more. `G` shows
+        /// and hides them.
+        #[arg(long)]
+        grid_lines: bool,
+
+        /// What to do when the window is resized: show more or less of the grid, or resize the
+        /// grid to fill the window at the current zoom, keeping the pattern centred.
+        #[arg(long, value_enum, default_value_t = Resize::View)]
+        on_resize: Resize,
+    },
+    /// Runs the simulation without a window for a number of generations, or until the pattern
+    /// settles, and prints how long it took and what became of the pattern.
+    Sim {
+        #[command(flatten)]
+        setup: Setup,
+
+        /// The number of generations to run for, at most.
+        #[arg(short, long, required_unless_present = "until_stable")]
+        generations: Option<u64>,
+
+        /// Stops once the pattern dies out, becomes stable or starts repeating itself, possibly
+        /// having moved. Checked every generation, so engines cannot skip ahead.
+        #[arg(short, long)]
+        until_stable: bool,
+
+        /// Prints the population every N generations.
+        #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+        report_every: Option<u64>,
+
+        /// Where to write the final grid, in the format given by its extension or as a `.ppm` image.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// A comment to include with the pattern. May be given more than once.
+        #[arg(short = 'C', long = "comment", value_name = "TEXT")]
+        comments: Vec<String>,
+    },
+    /// Runs the simulation without a window, and writes the grid at the given generation, trimmed
+    /// to its live cells.
+    Dump {
+        #[command(flatten)]
+        setup: Setup,
+
+        /// The generation to write.
+        #[arg(short, long)]
+        generation: u64,
+
+        /// Where to write the pattern, in the format given by its extension or as a `.ppm` image,
+        /// instead of writing RLE to standard output.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// A comment to include with the pattern. May be given more than once.
+        #[arg(short = 'C', long = "comment", value_name = "TEXT")]
+        comments: Vec<String>,
+    },
+    /// Times every mode on the same random soup, as a quick alternative to the criterion
+    /// benchmarks.
+    Bench(Bench),
+    /// Converts a pattern file from one format to another.
+    Convert {
+        /// The pattern file to read, in any supported format.
+        input: PathBuf,
+
+        /// The file to write, in the format given by its extension, or RLE if it has none.
+        output: PathBuf,
+    },
+    /// Describes a pattern file: its metadata, rule, bounding box, population and period.
+    Info {
+        /// The pattern file to describe.
+        file: PathBuf,
+
+        /// How many generations to run the pattern for while looking for its period.
+        #[arg(short, long, default_value_t = 1000)]
+        generations: u64,
+    },
 }
 
+#[derive(Args)]
+struct Bench {
+    /// The size of the grid, in cells.
+    #[arg(short, long, value_parser = parse_window_size, default_value = "1024x1024")]
+    size: (usize, usize),
+
+    /// The number of generations to time each mode over.
+    #[arg(short, long, default_value_t = 100)]
+    generations: u64,
+
+    /// The number of threads the concurrent modes use. Defaults to one per available core.
+    #[arg(short, long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+
+    /// The chunk size for the [Workers] and [Pool] modes.
+    #[arg(short, long, default_value_t = DEFAULT_CHUNK_SIZE)]
+    chunk_size: usize,
+
+    /// The side length, in cells, of the tiles used to skip regions that have stopped changing.
+    #[arg(long, default_value_t = DEFAULT_TILE_SIZE, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    tile_size: usize,
+
+    /// The rule to simulate. Defaults to B3/S23.
+    #[arg(short, long)]
+    rule: Option<Rule>,
+
+    /// The modes to time, separated by commas. Defaults to every mode that can simulate the rule.
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    modes: Vec<Mode>,
+
+    /// The seed of the soup, as for `run --soup`.
+    #[arg(long, value_name = "SEED", default_value = "0")]
+    soup: String,
+
+    /// The chance of each cell of the soup being alive, from 0 to 1.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_density)]
+    density: f64,
+}
 fn main() {
-    let cli = CLI::parse();
-    let (width, height) = cli.size;
-    let grid_height = height - TEXT_HEIGHT;
+    let mut matches = Cli::command().get_matches();
+    if matches.subcommand().is_none() && !matches.get_flag("list_patterns") {
+        // With no command (and so no options, which all belong to one), open a window.
+        matches = Cli::command().get_matches_from(env::args_os().take(1).chain(["run".into()]));
+    }
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let (Some(command), Some((_, matches))) = (cli.command, matches.subcommand()) else {
+        let width = library::PATTERNS.iter().map(|entry| entry.name.len()).max().unwrap_or(0);
+        for entry in library::PATTERNS {
+            println!("{:width$}  {}", entry.name, entry.description);
+        }
+        return;
+    };
+
+    let could_not_write = |path: &Path, e: io::Error| -> ! {
+        Cli::command().error(ErrorKind::Io, format!("Could not write {}: {e}", path.display())).exit()
+    };
+    match command {
+        Command::Run { setup, step_log, steps_per_frame, speed, paused, zoom, grid_lines, on_resize } => {
+            let playback = Playback::new(paused, steps_per_frame, speed);
+            run(&setup, matches, step_log, playback, zoom, grid_lines, on_resize)
+        }
+        Command::Sim { setup, generations, until_stable, report_every, output, comments } => {
+            let (mut stepper, config) = start(&setup, matches, true);
+            simulate(stepper.as_mut(), setup.mode, &config, generations, until_stable, report_every);
+            if let Some(path) = output {
+                write_grid(stepper.as_ref(), config.rule, &comments, &path).unwrap_or_else(|e| could_not_write(&path, e));
+            }
+        }
+        Command::Dump { setup, generation, output, comments } => {
+            let (mut stepper, config) = start(&setup, matches, true);
+            stepper.step_n(generation);
+            match output {
+                Some(path) => {
+                    write_grid(stepper.as_ref(), config.rule, &comments, &path).unwrap_or_else(|e| could_not_write(&path, e))
+                }
+                None => print!("{}", rle::encode(&snapshot(stepper.as_ref(), config.rule, &comments))),
+            }
+        }
+        Command::Bench(options) => bench(&options),
+        Command::Convert { input, output } => {
+            let pattern = pattern::read(&input).unwrap_or_else(|e| could_not_load(&input, e));
+            pattern::write(&output, &pattern).unwrap_or_else(|e| could_not_write(&output, e));
+        }
+        Command::Info { file, generations } => info(&file, generations),
+    }
+}
+
+/// Exits, reporting that the pattern file at [path] could not be read.
+fn could_not_load(path: &Path, e: ReadError) -> ! {
+    Cli::command().error(ErrorKind::InvalidValue, format!("Could not load {}: {e}", path.display())).exit()
+}
+
+/// How the engines should divide up the work of [mode], as configured by [setup].
+fn tuning(setup: &Setup, mode: Mode) -> Tuning {
+    let available_threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    Tuning {
+        num_threads: if mode.is_concurrent() { available_threads } else { 1 },
+        chunk_size: setup.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+        tile_size: setup.tile_size,
+    }
+}
+
+/// Seeds the grid described by [setup] and builds the engine to evolve it, exiting if the seeds
+/// cannot be loaded or the engine cannot simulate the rule. Without `--grid`, the grid fills the
+/// window, or if [headless] is just large enough to hold the seeds, see [fitted_grid_size].
+fn start(setup: &Setup, matches: &ArgMatches, headless: bool) -> (Box<dyn Stepper>, Config) {
+    let mode = setup.mode;
+    let seeds = seeds(setup, matches);
+    let placements = placements(setup, matches, &seeds);
+    // A lone macrocell left where it is goes straight into HashLife, so is never cut down to the grid.
+    let universe = match (&seeds[..], &placements[..]) {
+        ([(_, Seed::File(path))], [placement])
+            if mode == Mode::Hashlife
+                && setup.soup.is_none()
+                && Format::from_extension(path) == Some(Format::Macrocell)
+                && *placement == Placement { oversize: placement.oversize, ..Placement::default() } =>
+        {
+            let macrocell = fs::read_to_string(path).map_err(ReadError::from).and_then(|text| macrocell::parse(&text));
+            Some(macrocell.unwrap_or_else(|e| could_not_load(path, e)))
+        }
+        _ => None,
+    };
+    let patterns: Vec<Pattern> = match universe {
+        Some(_) => Vec::new(),
+        None => seeds
+            .iter()
+            .map(|(_, seed)| match seed {
+                Seed::File(path) => pattern::read(path).unwrap_or_else(|e| could_not_load(path, e)),
+                Seed::Library(entry) => entry.pattern(),
+            })
+            .collect(),
+    };
+
+    let fitted = headless && setup.grid.is_none();
+    let (width, grid_height) =
+        if fitted { fitted_grid_size(setup, &patterns, &placements, universe.as_ref()) } else { grid_size(setup) };
+    let mut grid = Grid::new(width, grid_height);
+    let soup = soup(setup, matches, if fitted { DEFAULT_SOUP_SIZE } else { (width, grid_height) });
+    if let Some(soup) = soup {
+        soup.pattern().place_centred(&mut grid, Oversize::Crop).unwrap();
+    }
+    let seed_rule = match universe {
+        Some(ref macrocell) => macrocell.rule,
+        None if seeds.is_empty() && soup.is_none() => {
+            seed_gosper(&mut grid.cells, width, grid_height);
+            None
+        }
+        None => {
+            let mut rule = None;
+            for (((_, seed), pattern), placement) in seeds.iter().zip(&patterns).zip(&placements) {
+                pattern.place_with(&mut grid, placement).unwrap_or_else(|e| {
+                    let message = format!("{seed}: {e} Pass --crop to crop it.");
+                    Cli::command().error(ErrorKind::InvalidValue, message).exit()
+                });
+                rule = rule.or(pattern.rule);
+            }
+            rule
+        }
+    };
+    let rule = setup.rule.or(seed_rule).unwrap_or_default();
+    let rule = match setup.neighbourhood {
+        Some(neighbourhood) => rule
+            .with_neighbourhood(neighbourhood)
+            .unwrap_or_else(|e| Cli::command().error(ErrorKind::ArgumentConflict, e).exit()),
+        None => rule,
+    };
+    let config = Config { rule, boundary: setup.boundary };
+
+    if !mode.supports(&config.rule) {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, format!("The {mode:?} mode cannot simulate {}.", config.rule))
+            .exit();
+    }
+
+    let stepper: Box<dyn Stepper> = match universe {
+        Some(macrocell) => {
+            let universe = HashLife::from_macrocell(&macrocell, config.rule);
+            Box::new(HashLifeStepper::from_universe(universe, width, grid_height))
+        }
+        None => mode.stepper(grid, &config, tuning(setup, mode)),
+    };
+    (stepper, config)
+}
+
+/// How the viewer advances the simulation: whether it is paused, how many generations it advances
+/// per frame, and how fast it may go.
+struct Playback {
+    paused: bool,
+    /// Whether to advance a single generation next frame, even though paused.
+    single_step: bool,
+    steps_per_frame: u64,
+    /// The target number of generations per second, and whether to hold to it.
+    speed: f64,
+    throttled: bool,
+    /// When the throttle was last reset, and the generations advanced since.
+    since: Instant,
+    advanced: u64,
+}
+
+impl Playback {
+    fn new(paused: bool, steps_per_frame: u64, speed: Option<f64>) -> Self {
+        Playback {
+            paused,
+            single_step: false,
+            steps_per_frame,
+            speed: speed.unwrap_or(DEFAULT_SPEED),
+            throttled: speed.is_some(),
+            since: Instant::now(),
+            advanced: 0,
+        }
+    }
+
+    /// Handles the playback keys pressed in [window].
+    fn handle_keys(&mut self, window: &Window) {
+        let pressed = |keys: &[Key]| keys.iter().any(|&key| window.is_key_pressed(key, KeyRepeat::No));
+        let speed = self.speed;
+        if pressed(&[Key::Space]) {
+            self.paused = !self.paused;
+        }
+        if pressed(&[Key::N]) {
+            (self.paused, self.single_step) = (true, true);
+        }
+        if pressed(&[Key::Equal, Key::NumPadPlus]) {
+            self.steps_per_frame = self.steps_per_frame.saturating_mul(2);
+        }
+        if pressed(&[Key::Minus, Key::NumPadMinus]) {
+            self.steps_per_frame = (self.steps_per_frame / 2).max(1);
+        }
+        if pressed(&[Key::T]) {
+            self.throttled = !self.throttled;
+        }
+        if pressed(&[Key::RightBracket]) {
+            (self.speed, self.throttled) = (self.speed * 2.0, true);
+        }
+        if pressed(&[Key::LeftBracket]) {
+            (self.speed, self.throttled) = ((self.speed / 2.0).max(1.0), true);
+        }
+        if self.paused || !self.throttled || self.speed != speed {
+            self.since = Instant::now();
+            self.advanced = 0;
+        }
+    }
+
+    /// The number of generations to advance this frame, at most [limit]. While throttled, that is
+    /// as many as have fallen due since the throttle was reset; any more than [limit] are dropped
+    /// rather than made up later.
+    fn generations(&mut self, limit: u64) -> u64 {
+        if self.paused {
+            return std::mem::take(&mut self.single_step) as u64;
+        }
+        if !self.throttled {
+            return limit;
+        }
+
+        let due = ((self.since.elapsed().as_secs_f64() * self.speed) as u64).saturating_sub(self.advanced);
+        let generations = due.min(limit);
+        self.advanced += generations;
+        if due > limit {
+            self.since = Instant::now();
+            self.advanced = 0;
+        }
+        generations
+    }
+
+    /// A summary for the status bar.
+    fn status(&self) -> String {
+        let mut text = if self.paused { "paused".to_string() } else { format!("{}/frame", self.steps_per_frame) };
+        if self.throttled {
+            text.push_str(&format!("; max {} gen/s", self.speed));
+        }
+        text
+    }
+}
+
+/// The size of the grid described by [setup], in cells: `--grid`, or else the view of the window.
+fn grid_size(setup: &Setup) -> (usize, usize) {
+    let (width, height) = setup.size;
+    setup.grid.unwrap_or_else(|| (width, view_height(height)))
+}
+
+/// The height of the view below the status bar of a window [height] pixels tall, exiting if there
+/// is no room for it.
+fn view_height(height: usize) -> usize {
+    height.checked_sub(TEXT_HEIGHT).filter(|&height| height > 0).unwrap_or_else(|| {
+        let message = format!("The window is {height} pixels tall, leaving no room below the {TEXT_HEIGHT} pixel status bar.");
+        Cli::command().error(ErrorKind::ValueValidation, message).exit()
+    })
+}
+
+/// The size of a grid that leaves [HEADLESS_MARGIN] dead cells around each of [patterns] where
+/// [placements] put it, around the soup, or around the Gosper gun placed when there is neither. Any
+/// placement works out as if the pattern were centred, and a lone [macrocell] is measured about
+/// the origin. Exits if the grid would hold more than [MAX_CELLS] cells.
+fn fitted_grid_size(setup: &Setup, patterns: &[Pattern], placements: &[Placement], macrocell: Option<&Macrocell>) -> (usize, usize) {
+    // Twice the distance from the middle of the grid to the further end of a span of cells.
+    let reach = |start: i64, end: i64| 2 * start.unsigned_abs().max(end.unsigned_abs()) as usize;
+    let mut size = (0, 0);
+    let mut fit = |(width, height): (usize, usize)| size = (size.0.max(width), size.1.max(height));
+
+    for (pattern, placement) in patterns.iter().zip(placements) {
+        let pattern = placement.flip.map_or_else(|| pattern.clone(), |flip| pattern.flipped(flip));
+        let pattern = pattern.rotated(placement.quarter_turns);
+        let (width, height) = (pattern.width as i64, pattern.height as i64);
+        let (left, top) = match (placement.anchor, pattern.offset) {
+            (Anchor::Origin, Some(offset)) => offset,
+            _ => (-(width / 2), -(height / 2)),
+        };
+        let (left, top) = (left + placement.at.0, top + placement.at.1);
+        fit((reach(left, left + width), reach(top, top + height)));
+    }
+    if let Some([left, top, right, bottom]) = macrocell.and_then(Macrocell::bounding_box) {
+        fit((reach(left, right + 1), reach(top, bottom + 1)));
+    }
+    if setup.soup.is_some() {
+        fit(setup.soup_size.unwrap_or(DEFAULT_SOUP_SIZE));
+    } else if patterns.is_empty() && macrocell.is_none() {
+        fit(GOSPER_GUN_SIZE);
+    }
+
+    let (width, height) = (size.0.saturating_add(2 * HEADLESS_MARGIN), size.1.saturating_add(2 * HEADLESS_MARGIN));
+    if width.checked_mul(height).is_none_or(|cells| cells > MAX_CELLS) {
+        let message = format!("The seeds need a {width}x{height} grid, which is too large. Pass --grid to choose a smaller one.");
+        Cli::command().error(ErrorKind::ValueValidation, message).exit();
+    }
+    (width, height)
+}
+
+/// How many pixels each cell takes up on screen, or how many cells share each pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Zoom {
+    /// Pixels per cell, along each axis.
+    In(usize),
+    /// Cells per pixel, along each axis, of which the pixel shows the average colour.
+    Out(usize),
+}
+
+impl Zoom {
+    /// Zooms in by a factor of two, up to [MAX_ZOOM_IN].
+    fn closer(self) -> Self {
+        match self {
+            Zoom::In(n) => Zoom::In((n * 2).min(MAX_ZOOM_IN)),
+            Zoom::Out(n) if n / 2 <= 1 => Zoom::In(1),
+            Zoom::Out(n) => Zoom::Out(n / 2),
+        }
+    }
+
+    /// Zooms out by a factor of two, down to [MAX_ZOOM_OUT].
+    fn further(self) -> Self {
+        match self {
+            Zoom::In(1) => Zoom::Out(2.min(MAX_ZOOM_OUT)),
+            Zoom::In(n) => Zoom::In(n / 2),
+            Zoom::Out(n) => Zoom::Out((n * 2).min(MAX_ZOOM_OUT)),
+        }
+    }
+
+    /// Pixels per cell, which may be a fraction.
+    fn scale(self) -> f64 {
+        match self {
+            Zoom::In(n) => n as f64,
+            Zoom::Out(n) => 1.0 / n as f64,
+        }
+    }
+}
+
+impl fmt::Display for Zoom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zoom::In(n) => write!(f, "{n} px/cell"),
+            Zoom::Out(n) => write!(f, "{n} cells/px"),
+        }
+    }
+}
+
+/// What the viewer does when its window is resized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Resize {
+    /// Shows more or less of the grid, keeping the middle of the view where it was.
+    View,
+    /// Replaces the grid with one that fills the window at the current zoom, with the pattern
+    /// copied into its middle, cropped if it no longer fits. The unbounded modes have no edges to
+    /// move, so they only re-fit the view.
+    Grid,
+}
+
+/// The part of the plane shown in the window: the cell at its top-left corner, which may lie
+/// part-way across a cell, and the zoom.
+struct Viewport {
+    origin: (f64, f64),
+    zoom: Zoom,
+    grid_lines: bool,
+    /// Where the view started, to return to with Home.
+    home: ((f64, f64), Zoom),
+    /// Where the mouse was last frame, while dragging the view.
+    drag: Option<(f32, f32)>,
+    /// The cells in view, fetched from the engine each frame.
+    cells: Vec<u8>,
+}
+
+impl Viewport {
+    /// A view at [zoom] of a [width] by [height] pixel area, centred on a grid of [grid] cells.
+    fn new(grid: (usize, usize), width: usize, height: usize, zoom: Zoom, grid_lines: bool) -> Self {
+        let mut viewport = Viewport { origin: (0.0, 0.0), zoom, grid_lines, home: ((0.0, 0.0), zoom), drag: None, cells: Vec::new() };
+        viewport.centre_on(grid, width, height);
+        viewport
+    }
+
+    /// Centres the view of a [width] by [height] pixel area on a grid of [grid] cells at the
+    /// current zoom, and makes that the view Home returns to.
+    fn centre_on(&mut self, grid: (usize, usize), width: usize, height: usize) {
+        let scale = self.zoom.scale();
+        let centre = |cells: usize, pixels: usize| (cells / 2) as f64 - (pixels as f64 / scale / 2.0).floor();
+        self.origin = (centre(grid.0, width), centre(grid.1, height));
+        self.home = (self.origin, self.zoom);
+    }
+
+    /// Re-fits the view to a pixel area resized from [old] to [new], keeping the cell in the
+    /// middle of it where it was, and likewise for the view Home returns to.
+    fn resize(&mut self, old: (usize, usize), new: (usize, usize)) {
+        let shift = |origin: &mut (f64, f64), scale: f64| {
+            origin.0 += (old.0 as f64 - new.0 as f64) / scale / 2.0;
+            origin.1 += (old.1 as f64 - new.1 as f64) / scale / 2.0;
+        };
+        shift(&mut self.origin, self.zoom.scale());
+        shift(&mut self.home.0, self.home.1.scale());
+    }
+
+    /// The size of a grid that would fill a [width] by [height] pixel area at the current zoom.
+    fn cells_to_fill(&self, width: usize, height: usize) -> (usize, usize) {
+        let scale = self.zoom.scale();
+        let cells = |pixels: usize| ((pixels as f64 / scale).ceil() as usize).max(1);
+        (cells(width), cells(height))
+    }
+
+    /// Pans the view with the arrow keys or by dragging with the left mouse button, and zooms it
+    /// with the mouse wheel, about the mouse, or with Page Up and Page Down, about the centre of
+    /// the [width] by [height] pixel area below the status bar.
+    fn handle_input(&mut self, window: &Window, width: usize, height: usize) {
+        let scale = self.zoom.scale();
+        for (key, (dx, dy)) in [(Key::Left, (-1.0, 0.0)), (Key::Right, (1.0, 0.0)), (Key::Up, (0.0, -1.0)), (Key::Down, (0.0, 1.0))] {
+            if window.is_key_pressed(key, KeyRepeat::Yes) {
+                self.origin.0 += dx * PAN_STEP / scale;
+                self.origin.1 += dy * PAN_STEP / scale;
+            }
+        }
+
+        let mouse = window.get_mouse_pos(MouseMode::Pass);
+        self.drag = match (self.drag, mouse) {
+            (last, Some(position)) if window.get_mouse_down(MouseButton::Left) => {
+                if let Some(last) = last {
+                    self.origin.0 -= (position.0 - last.0) as f64 / scale;
+                    self.origin.1 -= (position.1 - last.1) as f64 / scale;
+                }
+                Some(position)
+            }
+            _ => None,
+        };
+
+        let centre = (width as f64 / 2.0, height as f64 / 2.0);
+        let mouse = mouse.map_or(centre, |(x, y)| (x as f64, y as f64 - TEXT_HEIGHT as f64));
+        let mut zoom = |closer: bool, (x, y): (f64, f64)| {
+            let cell = (self.origin.0 + x / self.zoom.scale(), self.origin.1 + y / self.zoom.scale());
+            self.zoom = if closer { self.zoom.closer() } else { self.zoom.further() };
+            self.origin = (cell.0 - x / self.zoom.scale(), cell.1 - y / self.zoom.scale());
+        };
+        match window.get_scroll_wheel() {
+            Some((_, dy)) if dy > 0.0 => zoom(true, mouse),
+            Some((_, dy)) if dy < 0.0 => zoom(false, mouse),
+            _ => {}
+        }
+        if window.is_key_pressed(Key::PageUp, KeyRepeat::Yes) {
+            zoom(true, centre);
+        }
+        if window.is_key_pressed(Key::PageDown, KeyRepeat::Yes) {
+            zoom(false, centre);
+        }
+
+        if window.is_key_pressed(Key::Home, KeyRepeat::No) {
+            (self.origin, self.zoom) = self.home;
+        }
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            self.grid_lines = !self.grid_lines;
+        }
+    }
+
+    /// Draws the cells of [stepper] in view into the [width] by [height] [pixels], coloured by
+    /// [palette]. Zoomed out, each pixel takes the average colour of its cells.
+    fn draw(&mut self, stepper: &dyn Stepper, palette: &[u32; 256], pixels: &mut [u32], width: usize, height: usize) {
+        let (left, top) = (self.origin.0.floor() as i64, self.origin.1.floor() as i64);
+        match self.zoom {
+            Zoom::In(n) => {
+                let shift_x = ((self.origin.0 - left as f64) * n as f64) as usize;
+                let shift_y = ((self.origin.1 - top as f64) * n as f64) as usize;
+                let (columns, rows) = ((width + shift_x).div_ceil(n), (height + shift_y).div_ceil(n));
+                self.cells.resize(columns * rows, 0);
+                stepper.write_region(left, top, columns, rows, &mut self.cells);
+
+                let lines = self.grid_lines && n >= GRID_LINE_ZOOM;
+                for (y, row) in pixels.chunks_exact_mut(width).take(height).enumerate() {
+                    let (y, cells) = (y + shift_y, &self.cells[(y + shift_y) / n * columns..]);
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let x = x + shift_x;
+                        *pixel = match lines && (x.is_multiple_of(n) || y.is_multiple_of(n)) {
+                            true => GRID_LINE_COLOUR,
+                            false => palette[cells[x / n] as usize],
+                        };
+                    }
+                }
+            }
+            Zoom::Out(n) => {
+                let (columns, rows) = (width * n, height * n);
+                self.cells.resize(columns * rows, 0);
+                stepper.write_region(left, top, columns, rows, &mut self.cells);
+
+                let mut sums = vec![[0u32; 3]; width];
+                for (y, row) in pixels.chunks_exact_mut(width).take(height).enumerate() {
+                    sums.fill([0; 3]);
+                    for cells in self.cells[y * n * columns..(y + 1) * n * columns].chunks_exact(columns) {
+                        for (sum, block) in sums.iter_mut().zip(cells.chunks_exact(n)) {
+                            for &cell in block {
+                                let colour = palette[cell as usize];
+                                sum[0] += colour >> 16 & 0xFF;
+                                sum[1] += colour >> 8 & 0xFF;
+                                sum[2] += colour & 0xFF;
+                            }
+                        }
+                    }
+                    let count = (n * n) as u32;
+                    for (pixel, [r, g, b]) in row.iter_mut().zip(&sums) {
+                        *pixel = (r / count) << 16 | (g / count) << 8 | (b / count);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The window's framebuffer: the status bar along the top, and the view of the grid below it.
+struct Screen {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+    drawer: FbFontRenderer,
+}
+
+impl Screen {
+    fn new(width: usize, height: usize) -> Self {
+        Screen { width, height, pixels: vec![0u32; width * height], drawer: font6x8::new_renderer(width, height, TEXT_COLOUR) }
+    }
+
+    /// The height of the view below the status bar.
+    fn view_height(&self) -> usize {
+        self.height - TEXT_HEIGHT
+    }
+}
+
+/// Opens a window and runs the simulation described by [setup] in it until it is closed, advancing
+/// it as [playback] says, and `2^step_log` times as fast in the [Hashlife] mode, starting at [zoom]
+/// with or without [grid_lines], and responding to the window being resized as [on_resize] says.
+fn run(
+    setup: &Setup,
+    matches: &ArgMatches,
+    step_log: u8,
+    mut playback: Playback,
+    zoom: Zoom,
+    grid_lines: bool,
+    on_resize: Resize,
+) {
+    let (width, height) = setup.size;
+    let (mut stepper, config) = start(setup, matches, false);
+    let mut viewport = Viewport::new(grid_size(setup), width, view_height(height), zoom, grid_lines);
 
     let mut window = Window::new(
         "Game of Life",
@@ -75,92 +878,392 @@ fn main() {
         .expect("Window could not be created.");
     window.set_target_fps(240);
 
-    let mut pixels = vec![0u32; width * height];
-
-    let drawer = font6x8::new_renderer(width, height, TEXT_COLOUR);
+    let mut screen = Screen::new(width, height);
     let mut frame_count = 0;
     let mut last_time = Instant::now();
     let mut fps = 0.0;
+    let mut last_generation = stepper.generation();
+    let mut generations_per_second = 0.0;
 
-    let num_threads = match cli.mode {
-        Mode::Serial => 1,
-        _ => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
-    };
 
-    let mut render_frame = |window: &mut Window, grid: &[u8]| {
-        for (pixel, &cell) in pixels[width * TEXT_HEIGHT..].iter_mut().zip(grid.iter()) {
-            *pixel = if cell == 1 { ALIVE_COLOUR } else { DEAD_COLOUR };
-        }
+    let palette: [u32; 256] = std::array::from_fn(|state| cell_colour(state as u8, &config.rule));
+    let mut render_frame = |window: &mut Window, screen: &mut Screen, stepper: &dyn Stepper, mode: Mode, playback: &Playback, viewport: &mut Viewport| {
+        let (width, view_height) = (screen.width, screen.view_height());
+        viewport.draw(stepper, &palette, &mut screen.pixels[width * TEXT_HEIGHT..], width, view_height);
+        let generation = stepper.generation();
 
         frame_count += 1;
         let elapsed = last_time.elapsed().as_secs_f64();
         if elapsed >= FPS_UPDATE_INTERVAL {
             fps = frame_count as f64 / elapsed;
+            generations_per_second = generation.saturating_sub(last_generation) as f64 / elapsed;
             frame_count = 0;
             last_time = Instant::now();
+            last_generation = generation;
         }
 
-        pixels[..width * TEXT_HEIGHT].fill(0);
-        let mut text = format!("mode: {:?}; fps: {fps:.2}; num_threads: {num_threads}", cli.mode);
-        if cli.mode == Mode::Workers || cli.mode == Mode::Pool {
-            let chunk_size = cli.chunk_size.unwrap();
+        screen.pixels[..width * TEXT_HEIGHT].fill(0);
+        let Tuning { num_threads, chunk_size, .. } = tuning(setup, mode);
+        let mut text = format!(
+            "mode: {mode:?}; rule: {}; boundary: {:?}; fps: {fps:.2}; num_threads: {num_threads}; gen: {generation}; \
+             gen/s: {generations_per_second:.0}; {}; zoom: {}",
+            config.rule,
+            config.boundary,
+            playback.status(),
+            viewport.zoom,
+        );
+        if let neighbourhood @ Neighbourhood::Custom(_) = config.rule.neighbourhood() {
+            text.push_str(&format!("; neighbourhood: {neighbourhood}"));
+        }
+        if mode == Mode::Workers || mode == Mode::Pool {
             text.push_str(&format!("; chunk_size: {chunk_size}"));
         }
-        drawer.draw_text(&mut pixels, 2, 2, &text);
-        window.update_with_buffer(&pixels, width, height).unwrap();
-    };
-
-    let init_grid = |buffer: &mut Vec<u8>| {
-        match cli.seed {
-            Some(path) => decode_rle_into_centered(path, buffer, width, grid_height).expect("Failed to decode RLE file"),
-            None => seed_gosper(buffer, width, grid_height),
+        if mode == Mode::Hashlife {
+            text.push_str(&format!("; step: 2^{}", step_log));
         }
+        if let Some(ActivityStats { active_tiles, total_tiles, .. }) = stepper.activity() {
+            text.push_str(&format!("; active: {active_tiles}/{total_tiles} tiles"));
+        }
+        screen.drawer.draw_text(&mut screen.pixels, 2, 2, &text);
+        window.update_with_buffer(&screen.pixels, width, screen.height).unwrap();
     };
 
-    match cli.mode {
-        Mode::Serial | Mode::Parallel | Mode::Workers => {
-            let mut curr_buffer = vec![0u8; width * grid_height];
-            let mut next_buffer = vec![0u8; width * grid_height];
-            init_grid(&mut curr_buffer);
-
-            while window.is_open() && !window.is_key_down(Key::Escape) {
-                match cli.mode {
-                    Mode::Serial => step_serial(&curr_buffer, &mut next_buffer, width, grid_height),
-                    Mode::Parallel => step_parallel(&curr_buffer, &mut next_buffer, num_threads, width, grid_height),
-                    Mode::Workers => step_workers(&curr_buffer, &mut next_buffer, num_threads, cli.chunk_size.unwrap(), width, grid_height),
-                    _ => unreachable!("Mode already filtered"),
-                }
+    let mut mode = setup.mode;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        playback.handle_keys(&window);
+        let limit = match mode {
+            Mode::Hashlife => playback.steps_per_frame.saturating_mul(1 << step_log),
+            _ => playback.steps_per_frame,
+        };
+        stepper.step_n(playback.generations(limit));
+
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            let modes = Mode::value_variants();
+            let position = modes.iter().position(|&m| m == mode).unwrap();
+            mode = (1..=modes.len())
+                .map(|offset| modes[(position + offset) % modes.len()])
+                .find(|m| m.supports(&config.rule))
+                .unwrap();
+            let generation = stepper.generation();
+            stepper = mode.stepper(stepper.into_grid(), &config, tuning(setup, mode));
+            stepper.set_generation(generation);
+        }
 
-                swap(&mut curr_buffer, &mut next_buffer);
-                render_frame(&mut window, &curr_buffer);
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            let path = PathBuf::from(format!("generation-{}.rle", stepper.generation()));
+            match pattern::write(&path, &snapshot(stepper.as_ref(), config.rule, &[])) {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(e) => eprintln!("Could not save {}: {e}", path.display()),
             }
         }
-        Mode::Pool => {
-            let mut curr_vec = vec![0u8; width * grid_height];
-            init_grid(&mut curr_vec);
-            let curr_buffer = Arc::new(RwLock::new(curr_vec));
-            let next_buffer = Arc::new(Mutex::new(vec![0u8; width * grid_height]));
-
-            let chunk_size = cli.chunk_size.unwrap_or(256);
-            let pool = initialise_pool(
-                Arc::clone(&curr_buffer),
-                Arc::clone(&next_buffer),
-                num_threads,
-                chunk_size,
-                width,
-                grid_height,
-            );
-
-            while window.is_open() && !window.is_key_down(Key::Escape) {
-                step_pool(&pool, &curr_buffer, &next_buffer);
-                render_frame(&mut window, &curr_buffer.read().unwrap());
+
+        // Minimised windows report a size of zero, and are left as they were until restored.
+        let (width, height) = window.get_size();
+        if (width, height) != (screen.width, screen.height) && width > 0 && height > TEXT_HEIGHT {
+            let old = (screen.width, screen.view_height());
+            screen = Screen::new(width, height);
+            let view = (width, screen.view_height());
+            if on_resize == Resize::Grid && !mode.is_unbounded() {
+                let (columns, rows) = viewport.cells_to_fill(view.0, view.1);
+                let (mut grid, generation) = (Grid::new(columns, rows), stepper.generation());
+                Pattern::from_grid(&stepper.into_grid()).place_centred(&mut grid, Oversize::Crop).unwrap();
+                stepper = mode.stepper(grid, &config, tuning(setup, mode));
+                stepper.set_generation(generation);
+                viewport.centre_on((columns, rows), view.0, view.1);
+            } else {
+                viewport.resize(old, view);
             }
         }
+
+        viewport.handle_input(&window, screen.width, screen.view_height());
+        render_frame(&mut window, &mut screen, stepper.as_ref(), mode, &playback, &mut viewport);
     }
 
     exit(0);
 }
 
+/// The current grid of [stepper] as a pattern trimmed to its live cells, placed relative to the
+/// middle of the grid and labelled with [rule], the generation and any further [comments].
+fn snapshot(stepper: &dyn Stepper, rule: Rule, comments: &[String]) -> Pattern {
+    let grid = stepper.grid();
+    let mut pattern = Pattern::from_region(&grid, grid.width, 0, 0, grid.width, grid.height);
+    pattern.offset = Some((-(grid.width as i64 / 2), -(grid.height as i64 / 2)));
+    pattern.rule = Some(rule);
+    pattern.comments.push(format!("Generation {}.", stepper.generation()));
+    pattern.comments.extend_from_slice(comments);
+    pattern.trimmed()
+}
+
+/// Advances [stepper] by up to [generations] generations, or forever, stopping early once the
+/// pattern repeats itself if [until_stable], and prints the population every [report_every]
+/// generations and a summary at the end. The summary warns if, while looking for a repeat, live
+/// cells reached the edge of the grid, where the boundary may have decided the outcome.
+fn simulate(
+    stepper: &mut dyn Stepper,
+    mode: Mode,
+    config: &Config,
+    generations: Option<u64>,
+    until_stable: bool,
+    report_every: Option<u64>,
+) {
+    let rule = config.rule;
+    let first = stepper.generation();
+    let last = first.saturating_add(generations.unwrap_or(u64::MAX));
+    let batch = if until_stable { 1 } else { report_every.unwrap_or(u64::MAX) };
+    let (width, height) = (stepper.grid().width, stepper.grid().height);
+    let mut history = History::default();
+    let mut cycle = None;
+    let mut reached_edge = None;
+    if until_stable {
+        let pattern = snapshot(stepper, rule, &[]);
+        reached_edge = touches_edge(&pattern, width, height).then_some(first);
+        history.record(first, &pattern);
+    }
+
+    let start = Instant::now();
+    while stepper.generation() < last && cycle.is_none() {
+        stepper.step_n(batch.min(last - stepper.generation()));
+        let generation = stepper.generation();
+        if until_stable {
+            let pattern = snapshot(stepper, rule, &[]);
+            reached_edge = reached_edge.or(touches_edge(&pattern, width, height).then_some(generation));
+            cycle = history.record(generation, &pattern);
+        }
+        if report_every.is_some_and(|every| (generation - first).is_multiple_of(every)) {
+            println!("Generation {generation}: population {}", stepper.population());
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let ran = stepper.generation() - first;
+    let rate = ran as f64 / elapsed.max(f64::MIN_POSITIVE);
+    println!("Ran {ran} generations in {elapsed:.3}s ({rate:.1} generations per second) with the {mode:?} mode.");
+    let pattern = snapshot(stepper, rule, &[]);
+    println!("Population {}.", stepper.population());
+    if pattern.population() > 0 {
+        println!("The live cells in the grid fit in a {}x{} box.", pattern.width, pattern.height);
+    }
+    match cycle {
+        Some(cycle) => println!("{}", describe(cycle)),
+        None if until_stable => println!("Did not settle by generation {}.", stepper.generation()),
+        None => {}
+    }
+    if let Some(generation) = reached_edge {
+        let consequence = if mode.is_unbounded() {
+            "cells beyond it were left out when looking for a repeat".to_string()
+        } else {
+            let boundary = config.boundary.to_possible_value().unwrap();
+            format!("what became of the pattern may be down to the {} boundary", boundary.get_name())
+        };
+        println!(
+            "Warning: live cells reached the edge of the {width}x{height} grid at generation {generation}, so {consequence}. \
+             Pass a larger --grid, or see `info` for what happens on an unbounded plane."
+        );
+    }
+}
+
+/// Whether [pattern], a [snapshot] of a [width] by [height] grid, has live cells in the outermost
+/// rows or columns of the grid.
+fn touches_edge(pattern: &Pattern, width: usize, height: usize) -> bool {
+    let Some((x, y)) = pattern.offset.filter(|_| pattern.population() > 0) else {
+        return false;
+    };
+    let (left, top) = (x + width as i64 / 2, y + height as i64 / 2);
+    left == 0 || top == 0 || left as usize + pattern.width == width || top as usize + pattern.height == height
+}
+
+/// What becomes of a pattern that enters [cycle].
+fn describe(cycle: Cycle) -> String {
+    match cycle {
+        Cycle { start, dead: true, .. } => format!("Dies out at generation {start}."),
+        Cycle { start, period: 1, .. } => format!("Stable from generation {start}."),
+        Cycle { start, period, displacement: (0, 0), .. } => format!("Oscillates with period {period} from generation {start}."),
+        Cycle { start, period, displacement: (x, y), .. } => {
+            format!("Moves by ({x}, {y}) every {period} generations from generation {start}.")
+        }
+    }
+}
+
+/// Times [Bench::generations] generations of the same soup in each mode asked for, printing the
+/// throughput of each and its final population. The bounded modes should all agree on the
+/// population; the unbounded ones simulate an infinite plane, so may not.
+fn bench(options: &Bench) {
+    let (width, height) = options.size;
+    let rule = options.rule.unwrap_or_default();
+    let config = Config { rule, ..Config::default() };
+    let modes = match &options.modes[..] {
+        [] => Mode::value_variants().iter().copied().filter(|mode| mode.supports(&rule)).collect(),
+        modes => modes.to_vec(),
+    };
+    if let Some(mode) = modes.iter().find(|mode| !mode.supports(&rule)) {
+        Cli::command().error(ErrorKind::ArgumentConflict, format!("The {mode:?} mode cannot simulate {rule}.")).exit();
+    }
+
+    let soup = Soup { width, height, density: options.density, symmetry: Symmetry::C1, seed: parse_seed(&options.soup) };
+    let cells = soup.pattern().cells;
+    let threads = options.threads.unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+    let plural = if threads == 1 { "" } else { "s" };
+    println!("{} generations of a {width}x{height} soup under {rule}, with {threads} thread{plural}.", options.generations);
+    println!("{:<10} {:>10} {:>14} {:>10} {:>11}", "mode", "time", "generations/s", "Mcells/s", "population");
+    for mode in modes {
+        let tuning = Tuning {
+            num_threads: if mode.is_concurrent() { threads } else { 1 },
+            chunk_size: options.chunk_size,
+            tile_size: options.tile_size,
+        };
+        let mut stepper = mode.stepper(Grid { cells: cells.clone(), width, height }, &config, tuning);
+        let start = Instant::now();
+        stepper.step_n(options.generations);
+        let elapsed = start.elapsed().as_secs_f64();
+
+        let rate = options.generations as f64 / elapsed.max(f64::MIN_POSITIVE);
+        let cells_per_second = rate * (width * height) as f64 / 1e6;
+        let name = format!("{mode:?}").to_lowercase();
+        println!("{name:<10} {elapsed:>9.3}s {rate:>14.1} {cells_per_second:>10.1} {:>11}", stepper.population());
+    }
+}
+
+/// Prints the metadata, rule, bounding box and population of the pattern file at [path], and what
+/// becomes of it within [generations] generations on an unbounded plane.
+fn info(path: &Path, generations: u64) {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| could_not_load(path, e.into()));
+    let format = Format::detect(path, &text);
+    let pattern = format.parse(&text).unwrap_or_else(|e| could_not_load(path, e)).trimmed();
+
+    println!("Format: {format}");
+    if let Some(name) = &pattern.name {
+        println!("Name: {name}");
+    }
+    if let Some(author) = &pattern.author {
+        println!("Author: {author}");
+    }
+    for comment in &pattern.comments {
+        println!("Comment: {comment}");
+    }
+    let rule = pattern.rule.unwrap_or_default();
+    match pattern.rule {
+        Some(rule) => println!("Rule: {rule}"),
+        None => println!("Rule: {rule} (not given)"),
+    }
+    println!("Bounding box: {}x{}", pattern.width, pattern.height);
+    if let Some((x, y)) = pattern.offset {
+        println!("Offset: {x}, {y}");
+    }
+    println!("Population: {}", pattern.population());
+
+    if !Mode::Sparse.supports(&rule) {
+        println!("The period is unknown, as {rule} cannot be simulated on an unbounded plane.");
+        return;
+    }
+    match cycle::find(&pattern, rule, generations) {
+        Some(cycle) => println!("{}", describe(cycle)),
+        None => println!("Does not settle within {generations} generations."),
+    }
+}
+
+/// Writes the grid of [stepper] to [path], in the format its extension gives: a `.ppm` image of the
+/// whole grid, coloured as in the window, a macrocell file of the whole universe if the engine has
+/// one, or else a pattern trimmed to its live cells, with [comments].
+fn write_grid(stepper: &dyn Stepper, rule: Rule, comments: &[String], path: &Path) -> io::Result<()> {
+    if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ppm")) {
+        return fs::write(path, ppm(&stepper.grid(), &rule));
+    }
+    match stepper.macrocell() {
+        Some(mut macrocell) if Format::from_extension(path) == Some(Format::Macrocell) => {
+            macrocell.comments.extend_from_slice(comments);
+            fs::write(path, macrocell::encode(&macrocell))
+        }
+        _ => pattern::write(path, &snapshot(stepper, rule, comments)),
+    }
+}
+
+/// Encodes [grid] as a binary PPM image, one pixel per cell.
+fn ppm(grid: &GridView, rule: &Rule) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", grid.width, grid.height).into_bytes();
+    for &cell in grid.iter() {
+        out.extend_from_slice(&cell_colour(cell, rule).to_be_bytes()[1..]);
+    }
+    out
+}
+
+/// The colour of a cell in [state] under [rule]: the rule table's own colour if it has one, and
+/// otherwise refractory states fade from [DYING_COLOUR] towards [DEAD_COLOUR] as they age.
+fn cell_colour(state: u8, rule: &Rule) -> u32 {
+    if let Some(colour) = rule.table().and_then(|table| table.colour(state)) {
+        return colour;
+    }
+    let states = rule.states();
+    match state {
+        0 => DEAD_COLOUR,
+        1 => ALIVE_COLOUR,
+        _ if state >= states => DEAD_COLOUR,
+        _ => {
+            let remaining = (states - state) as u32;
+            let fade = |shift: u32| ((DYING_COLOUR >> shift & 0xFF) * remaining / (states as u32 - 2)) << shift;
+            fade(16) | fade(8) | fade(0)
+        }
+    }
+}
+
+/// The soup asked for by `--soup`, if any, for a grid of [size].
+fn soup(setup: &Setup, matches: &ArgMatches, size: (usize, usize)) -> Option<Soup> {
+    let Some(seed) = &setup.soup else {
+        let options = ["soup_size", "density", "symmetry"];
+        if let Some(option) = options.iter().find(|&&id| matches.value_source(id) == Some(ValueSource::CommandLine)) {
+            let message = format!("--{} shapes a --soup, but none was given.", option.replace('_', "-"));
+            Cli::command().error(ErrorKind::MissingRequiredArgument, message).exit();
+        }
+        return None;
+    };
+
+    let (width, height) = setup.soup_size.unwrap_or(size);
+    if setup.symmetry.needs_square() && width != height {
+        let message = format!("{:?} soups must be square, but this one is {width}x{height}.", setup.symmetry);
+        Cli::command().error(ErrorKind::ArgumentConflict, message).exit();
+    }
+    Some(Soup { width, height, density: setup.density, symmetry: setup.symmetry, seed: parse_seed(seed) })
+}
+
+/// Every `--seed` and `--pattern`, with its index among the arguments, in the order given.
+fn seeds<'a>(setup: &'a Setup, matches: &ArgMatches) -> Vec<(usize, Seed<'a>)> {
+    let files = matches.indices_of("seed").into_iter().flatten().zip(setup.seed.iter().map(Seed::File));
+    let library = matches.indices_of("pattern").into_iter().flatten().zip(setup.pattern.iter().map(|&entry| Seed::Library(entry)));
+    let mut seeds: Vec<_> = files.chain(library).collect();
+    seeds.sort_by_key(|&(index, _)| index);
+    seeds
+}
+
+/// The placement of each of [seeds]. Each placement option applies to the nearest seed before it,
+/// or to every seed if it comes before them all.
+fn placements(setup: &Setup, matches: &ArgMatches, seeds: &[(usize, Seed)]) -> Vec<Placement> {
+    let oversize = if setup.crop { Oversize::Crop } else { Oversize::Reject };
+    let seeds: Vec<usize> = seeds.iter().map(|&(index, _)| index).collect();
+    let options = ["anchor", "at", "flip", "rotate", "tile"];
+    if let Some(option) = options.iter().find(|&&id| seeds.is_empty() && matches.contains_id(id)) {
+        let message = format!("--{option} places a --seed or --pattern, but none was given.");
+        Cli::command().error(ErrorKind::MissingRequiredArgument, message).exit();
+    }
+    let mut placements = vec![Placement { oversize, ..Placement::default() }; seeds.len()];
+    let mut apply = |id: &str, set: &dyn Fn(&mut Placement, usize)| {
+        for (occurrence, index) in matches.indices_of(id).into_iter().flatten().enumerate() {
+            let targets = match seeds.iter().rposition(|&seed| seed < index) {
+                Some(seed) => seed..seed + 1,
+                None => 0..seeds.len(),
+            };
+            for placement in &mut placements[targets] {
+                set(placement, occurrence);
+            }
+        }
+    };
+    apply("anchor", &|placement, i| placement.anchor = setup.anchor[i]);
+    apply("at", &|placement, i| placement.at = setup.at[i]);
+    apply("flip", &|placement, i| placement.flip = Some(setup.flip[i]));
+    apply("rotate", &|placement, i| placement.quarter_turns = setup.rotate[i]);
+    apply("tile", &|placement, i| placement.tile = Some(setup.tile[i]));
+    placements
+}
+
 fn parse_window_size(s: &str) -> Result<(usize, usize), String> {
     let mut parts = s.split('x');
     let width = parts
@@ -175,3 +1278,50 @@ fn parse_window_size(s: &str) -> Result<(usize, usize), String> {
         .map_err(|e| format!("Invalid height: {e}"))?;
     Ok((width, height))
 }
+
+fn parse_offset(s: &str) -> Result<(i64, i64), String> {
+    let (x, y) = s.split_once(',').ok_or_else(|| "Expected 'X,Y'.".to_string())?;
+    let x = x.trim().parse::<i64>().map_err(|e| format!("Invalid X: {e}"))?;
+    let y = y.trim().parse::<i64>().map_err(|e| format!("Invalid Y: {e}"))?;
+    Ok((x, y))
+}
+
+fn parse_pattern(s: &str) -> Result<&'static Entry, String> {
+    library::get(s).ok_or_else(|| format!("Unknown pattern '{s}'. Pass --list-patterns to see them all."))
+}
+
+fn parse_zoom(s: &str) -> Result<Zoom, String> {
+    let (n, max, zoom): (&str, usize, fn(usize) -> Zoom) = match s.trim().strip_prefix("1/") {
+        Some(n) => (n, MAX_ZOOM_OUT, Zoom::Out),
+        None => (s.trim(), MAX_ZOOM_IN, Zoom::In),
+    };
+    match n.parse::<usize>() {
+        Ok(1) => Ok(Zoom::In(1)),
+        Ok(n) if (2..=max).contains(&n) => Ok(zoom(n)),
+        _ => Err(format!(
+            "Invalid zoom '{s}': expected N pixels per cell, up to {MAX_ZOOM_IN}, or 1/N cells per pixel, up to 1/{MAX_ZOOM_OUT}."
+        )),
+    }
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("Invalid speed '{s}': expected a positive number of generations per second.")),
+    }
+}
+
+fn parse_density(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(density) if (0.0..=1.0).contains(&density) => Ok(density),
+        _ => Err(format!("Invalid density '{s}': expected a number from 0 to 1.")),
+    }
+}
+
+/// Parses a rotation in degrees into a number of quarter turns.
+fn parse_rotation(s: &str) -> Result<u8, String> {
+    match s.trim().parse::<u16>() {
+        Ok(degrees @ (0 | 90 | 180 | 270)) => Ok((degrees / 90) as u8),
+        _ => Err(format!("Invalid rotation '{s}': expected 0, 90, 180 or 270.")),
+    }
+}
@@ -0,0 +1,200 @@
+//! Neighbourhoods, deciding which nearby cells count towards a cell's neighbour count.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The furthest a neighbour in a custom [Kernel] may lie from its cell along either axis.
+pub const MAX_RANGE: usize = 7;
+
+/// The largest range of the extended neighbourhoods, which keeps neighbour counts within a [u16].
+pub const MAX_EXTENDED_RANGE: u8 = 127;
+
+/// The side length of the square of offsets a [Kernel] can hold.
+const WINDOW: usize = 2 * MAX_RANGE + 1;
+
+const MOORE: [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+const VON_NEUMANN: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const HEXAGONAL: [(isize, isize); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Neighbourhood {
+    /// The eight surrounding cells.
+    #[default]
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
+    /// Six cells, emulating a hexagonal grid on a square one by skewing it: the north-east and
+    /// south-west diagonals are left out, as in Golly.
+    Hexagonal,
+    /// Every cell within the given range along both axes, i.e. a square without its centre.
+    ExtendedMoore(u8),
+    /// Every cell within the given Manhattan distance, i.e. a diamond without its centre.
+    ExtendedVonNeumann(u8),
+    /// An arbitrary set of offsets.
+    Custom(Kernel),
+}
+
+/// A set of neighbour offsets, each at most [MAX_RANGE] from the centre along either axis. Bit
+/// `(dy + MAX_RANGE) * WINDOW + (dx + MAX_RANGE)` is set when `(dx, dy)` is a neighbour.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Kernel {
+    bits: [u64; 4],
+}
+
+impl Kernel {
+    /// Collects [offsets] into a kernel, rejecting the centre and anything out of range. Repeated
+    /// offsets count once.
+    pub fn from_offsets(offsets: &[(isize, isize)]) -> Result<Self, String> {
+        let mut bits = [0u64; 4];
+        for &(dx, dy) in offsets {
+            if (dx, dy) == (0, 0) {
+                return Err("A cell cannot be its own neighbour.".to_string());
+            }
+            if dx.unsigned_abs() > MAX_RANGE || dy.unsigned_abs() > MAX_RANGE {
+                return Err(format!("Offset ({dx}, {dy}) is further than {MAX_RANGE} cells away."));
+            }
+            let bit = (dy + MAX_RANGE as isize) as usize * WINDOW + (dx + MAX_RANGE as isize) as usize;
+            bits[bit / 64] |= 1 << (bit % 64);
+        }
+        Ok(Kernel { bits })
+    }
+}
+
+impl Neighbourhood {
+    /// The offsets of a cell's neighbours.
+    pub fn offsets(&self) -> Offsets {
+        match *self {
+            Neighbourhood::Moore => Offsets::Fixed(MOORE.iter()),
+            Neighbourhood::VonNeumann => Offsets::Fixed(VON_NEUMANN.iter()),
+            Neighbourhood::Hexagonal => Offsets::Fixed(HEXAGONAL.iter()),
+            Neighbourhood::ExtendedMoore(range) => {
+                let range = range as isize;
+                Offsets::Square { range, dx: -range, dy: -range, diamond: false }
+            }
+            Neighbourhood::ExtendedVonNeumann(range) => {
+                let range = range as isize;
+                Offsets::Square { range, dx: -range, dy: -range, diamond: true }
+            }
+            Neighbourhood::Custom(kernel) => Offsets::Bits { bits: kernel.bits, word: 0 },
+        }
+    }
+
+    /// The number of neighbours a cell has.
+    pub fn size(&self) -> usize {
+        match *self {
+            Neighbourhood::ExtendedMoore(range) => (2 * range as usize + 1).pow(2) - 1,
+            Neighbourhood::ExtendedVonNeumann(range) => 2 * range as usize * (range as usize + 1),
+            _ => self.offsets().count(),
+        }
+    }
+
+    /// The furthest any neighbour lies from its cell along either axis.
+    pub fn range(&self) -> usize {
+        match *self {
+            Neighbourhood::ExtendedMoore(range) | Neighbourhood::ExtendedVonNeumann(range) => range as usize,
+            _ => self.offsets().map(|(dx, dy)| dx.unsigned_abs().max(dy.unsigned_abs())).max().unwrap_or(0),
+        }
+    }
+}
+
+/// An iterator over the offsets of a [Neighbourhood], see [Neighbourhood::offsets].
+pub enum Offsets {
+    Fixed(std::slice::Iter<'static, (isize, isize)>),
+    /// Scans the square of the given range row by row, keeping only the diamond if asked.
+    Square { range: isize, dx: isize, dy: isize, diamond: bool },
+    Bits { bits: [u64; 4], word: usize },
+}
+
+impl Iterator for Offsets {
+    type Item = (isize, isize);
+
+    fn next(&mut self) -> Option<(isize, isize)> {
+        match self {
+            Offsets::Fixed(offsets) => offsets.next().copied(),
+            Offsets::Square { range, dx, dy, diamond } => loop {
+                if *dy > *range {
+                    return None;
+                }
+                let offset = (*dx, *dy);
+                if *dx == *range {
+                    (*dx, *dy) = (-*range, *dy + 1);
+                } else {
+                    *dx += 1;
+                }
+                if offset != (0, 0) && (!*diamond || offset.0.abs() + offset.1.abs() <= *range) {
+                    return Some(offset);
+                }
+            },
+            Offsets::Bits { bits, word } => {
+                while *word < bits.len() && bits[*word] == 0 {
+                    *word += 1;
+                }
+                let remaining = bits.get_mut(*word)?;
+                let bit = *word * 64 + remaining.trailing_zeros() as usize;
+                *remaining &= *remaining - 1;
+                let centre = MAX_RANGE as isize;
+                Some(((bit % WINDOW) as isize - centre, (bit / WINDOW) as isize - centre))
+            }
+        }
+    }
+}
+
+/// Accepts `moore`, `von-neumann`, `hexagonal`, `moore:R` or `von-neumann:R` for the extended
+/// neighbourhoods of range R, or a custom list of offsets such as `-1,0;1,0;0,-2;0,2`.
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "moore" | "m" => return Ok(Neighbourhood::Moore),
+            "von-neumann" | "vonneumann" | "v" => return Ok(Neighbourhood::VonNeumann),
+            "hexagonal" | "hex" | "h" => return Ok(Neighbourhood::Hexagonal),
+            _ => {}
+        }
+
+        if let Some(range) = s.strip_prefix("moore:") {
+            return parse_range(range).map(Neighbourhood::ExtendedMoore);
+        }
+        if let Some(range) = s.strip_prefix("von-neumann:") {
+            return parse_range(range).map(Neighbourhood::ExtendedVonNeumann);
+        }
+
+        let offsets = s
+            .split(';')
+            .map(|offset| {
+                let parse = |value: &str| value.trim().parse::<isize>().ok();
+                match offset.split_once(',') {
+                    Some((dx, dy)) => parse(dx).zip(parse(dy)),
+                    None => None,
+                }
+                .ok_or_else(|| format!("Invalid offset '{offset}': expected 'dx,dy'."))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Kernel::from_offsets(&offsets).map(Neighbourhood::Custom)
+    }
+}
+
+/// Parses the range of an extended neighbourhood.
+pub(crate) fn parse_range(s: &str) -> Result<u8, String> {
+    match s.trim().parse::<u8>() {
+        Ok(range) if (1..=MAX_EXTENDED_RANGE).contains(&range) => Ok(range),
+        _ => Err(format!("Invalid range '{s}': expected a number from 1 to {MAX_EXTENDED_RANGE}.")),
+    }
+}
+
+impl fmt::Display for Neighbourhood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Neighbourhood::Moore => write!(f, "moore"),
+            Neighbourhood::VonNeumann => write!(f, "von-neumann"),
+            Neighbourhood::Hexagonal => write!(f, "hexagonal"),
+            Neighbourhood::ExtendedMoore(range) => write!(f, "moore:{range}"),
+            Neighbourhood::ExtendedVonNeumann(range) => write!(f, "von-neumann:{range}"),
+            Neighbourhood::Custom(_) => {
+                let offsets: Vec<String> = self.offsets().map(|(dx, dy)| format!("{dx},{dy}")).collect();
+                write!(f, "{}", offsets.join(";"))
+            }
+        }
+    }
+}
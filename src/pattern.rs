@@ -0,0 +1,465 @@
+//! Patterns read from or written to files, kept apart from any grid until they are placed into
+//! one. Each file format has its own module, see [Format].
+
+use crate::rule::Rule;
+use crate::macrocell::{self, Macrocell};
+use crate::{lif, plaintext, rle, Grid};
+use clap::ValueEnum;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A rectangle of cells with the metadata that came with it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    /// The cells of the bounding box given by the file, stored row-major.
+    pub cells: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    /// Free-form comment lines, in order.
+    pub comments: Vec<String>,
+    /// The rule the pattern was written for, if the file names one.
+    pub rule: Option<Rule>,
+    /// Where the file places the top-left corner of the bounding box, relative to the origin.
+    pub offset: Option<(i64, i64)>,
+}
+
+/// The largest area [Pattern::from_cells] and the parsers will allocate.
+pub const MAX_CELLS: usize = 1 << 30;
+
+/// What to do with a pattern that does not fit in the grid it is placed into.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Oversize {
+    /// Refuse to place it, leaving the grid untouched.
+    #[default]
+    Reject,
+    /// Place whatever part of it lands on the grid.
+    Crop,
+}
+
+/// Which point of a pattern is lined up with the same point of the grid it is placed into.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Anchor {
+    #[default]
+    Centre,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    /// The pattern's own offset, taking the middle of the grid as the origin, so that saved
+    /// patterns go back where they were. Patterns without one are centred.
+    Origin,
+}
+
+impl Anchor {
+    /// Where the top-left corner of [pattern] goes in a [width] by [height] grid.
+    pub fn position(self, pattern: &Pattern, width: usize, height: usize) -> (i64, i64) {
+        let (width, height) = (width as i64, height as i64);
+        let (right, bottom) = (width - pattern.width as i64, height - pattern.height as i64);
+        let (middle, centre) = (width / 2 - (pattern.width / 2) as i64, height / 2 - (pattern.height / 2) as i64);
+        match self {
+            Anchor::Centre => (middle, centre),
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (middle, 0),
+            Anchor::TopRight => (right, 0),
+            Anchor::Left => (0, centre),
+            Anchor::Right => (right, centre),
+            Anchor::BottomLeft => (0, bottom),
+            Anchor::Bottom => (middle, bottom),
+            Anchor::BottomRight => (right, bottom),
+            Anchor::Origin => pattern.offset.map_or((middle, centre), |(x, y)| (width / 2 + x, height / 2 + y)),
+        }
+    }
+}
+
+/// A mirror image of a pattern.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Flip {
+    LeftRight,
+    TopBottom,
+}
+
+/// How to put a pattern into a grid, see [Pattern::place_with]. The pattern is flipped, then
+/// rotated, then lined up by its anchor, then moved.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Placement {
+    pub anchor: Anchor,
+    /// How far to move the pattern from where [Placement::anchor] puts it.
+    pub at: (i64, i64),
+    pub flip: Option<Flip>,
+    /// How many right angles to turn the pattern clockwise by.
+    pub quarter_turns: u8,
+    /// Repeat the pattern across the whole grid, with this many dead cells between copies.
+    pub tile: Option<usize>,
+    pub oversize: Oversize,
+}
+
+/// A pattern was too large for its grid, see [Oversize::Reject].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TooLarge {
+    pub pattern: (usize, usize),
+    pub grid: (usize, usize),
+}
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((pw, ph), (gw, gh)) = (self.pattern, self.grid);
+        write!(f, "The {pw}x{ph} pattern does not fit in the {gw}x{gh} grid.")
+    }
+}
+
+impl Error for TooLarge {}
+
+/// Why a pattern file could not be read. Lines and columns count from 1.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// There was nothing but comments, or the first line was not the one the format requires.
+    MissingHeader { expected: &'static str },
+    /// A header line was malformed, such as the `x = .., y = ..` of an RLE file or the `#G` of a
+    /// macrocell file.
+    InvalidHeader { line: usize, message: String },
+    /// The rule named in the file could not be parsed.
+    InvalidRule { line: usize, message: String },
+    /// An offset line, or a cell in a list of coordinates, did not hold two coordinates.
+    InvalidCoordinates { line: usize },
+    /// A character with no meaning in the cells.
+    UnexpectedCharacter { line: usize, column: usize, found: char },
+    /// A state above 255 or beyond the pattern's rule, or a prefix such as `p` not followed by a
+    /// state letter.
+    InvalidState { line: usize, column: usize },
+    /// A live cell outside the bounding box given in an RLE header.
+    OutOfBounds { line: usize, column: usize },
+    /// A node of a macrocell file that was malformed or referred to a node it could not contain.
+    InvalidNode { line: usize, message: String },
+    /// The live cells spread over more than [MAX_CELLS] cells, too many to hold in a grid.
+    TooLarge,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "{e}"),
+            ReadError::MissingHeader { expected } => write!(f, "missing the '{expected}' header"),
+            ReadError::InvalidHeader { line, message } => write!(f, "line {line}: invalid header: {message}"),
+            ReadError::InvalidRule { line, message } => write!(f, "line {line}: invalid rule: {message}"),
+            ReadError::InvalidCoordinates { line } => write!(f, "line {line}: expected two coordinates"),
+            ReadError::UnexpectedCharacter { line, column, found } => {
+                write!(f, "line {line}, column {column}: unexpected character '{found}'")
+            }
+            ReadError::InvalidState { line, column } => write!(f, "line {line}, column {column}: invalid state"),
+            ReadError::OutOfBounds { line, column } => {
+                write!(f, "line {line}, column {column}: cell outside the bounding box in the header")
+            }
+            ReadError::InvalidNode { line, message } => write!(f, "line {line}: invalid node: {message}"),
+            ReadError::TooLarge => write!(f, "the pattern spreads over too large an area to hold in a grid"),
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+/// The file formats patterns can be read from and written to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Run-length encoded, see [rle].
+    Rle,
+    /// Rows of `.` and `O`, see [plaintext].
+    Plaintext,
+    /// Blocks of rows of `.` and `*`, see [lif].
+    Life105,
+    /// A list of the coordinates of live cells, see [lif].
+    Life106,
+    /// A quadtree, see [macrocell].
+    Macrocell,
+}
+
+impl Format {
+    /// The format usually given the extension of [path]. Life 1.05 and 1.06 files share `.lif`
+    /// and `.life`, which are taken to mean Life 1.06, the more common of the two.
+    pub fn from_extension(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rle" => Some(Format::Rle),
+            "cells" => Some(Format::Plaintext),
+            "lif" | "life" => Some(Format::Life106),
+            "mc" => Some(Format::Macrocell),
+            _ => None,
+        }
+    }
+
+    /// The format of the file at [path] holding [text]. A `#Life` or `[M2]` header settles it, then
+    /// the extension, and otherwise RLE is assumed unless the text looks like plaintext.
+    pub fn detect(path: &Path, text: &str) -> Format {
+        let first = text.lines().next().unwrap_or("").trim();
+        if first.starts_with(lif::HEADER_105) {
+            return Format::Life105;
+        }
+        if first.starts_with(lif::HEADER_106) {
+            return Format::Life106;
+        }
+        if first.starts_with(macrocell::HEADER) {
+            return Format::Macrocell;
+        }
+        if let Some(format) = Format::from_extension(path) {
+            return format;
+        }
+        let plain = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .all(|line| line.starts_with('!') || line.chars().all(|ch| matches!(ch, '.' | 'O' | 'o' | '*')));
+        if plain { Format::Plaintext } else { Format::Rle }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Pattern, ReadError> {
+        match self {
+            Format::Rle => rle::parse(text),
+            Format::Plaintext => plaintext::parse(text),
+            Format::Life105 => lif::parse_105(text),
+            Format::Life106 => lif::parse_106(text),
+            Format::Macrocell => macrocell::parse(text)?.to_pattern().ok_or(ReadError::TooLarge),
+        }
+    }
+
+    pub fn encode(self, pattern: &Pattern) -> String {
+        match self {
+            Format::Rle => rle::encode(pattern),
+            Format::Plaintext => plaintext::encode(pattern),
+            Format::Life105 => lif::encode_105(pattern),
+            Format::Life106 => lif::encode_106(pattern),
+            Format::Macrocell => macrocell::encode(&Macrocell::from_pattern(pattern)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Rle => "RLE",
+            Format::Plaintext => "plaintext",
+            Format::Life105 => "Life 1.05",
+            Format::Life106 => "Life 1.06",
+            Format::Macrocell => "macrocell",
+        })
+    }
+}
+
+/// Reads the pattern file at [path], in whichever format it turns out to be, see [Format::detect].
+pub fn read(path: &Path) -> Result<Pattern, ReadError> {
+    let text = fs::read_to_string(path)?;
+    Format::detect(path, &text).parse(&text)
+}
+
+/// Writes [pattern] to the file at [path], in the format given by its extension, or as RLE.
+pub fn write(path: &Path, pattern: &Pattern) -> io::Result<()> {
+    let format = Format::from_extension(path).unwrap_or(Format::Rle);
+    fs::write(path, format.encode(pattern))
+}
+
+impl Pattern {
+    /// An empty [width] by [height] pattern with no metadata.
+    pub fn new(width: usize, height: usize) -> Self {
+        Pattern { cells: vec![0; width * height], width, height, ..Pattern::default() }
+    }
+
+    /// Copies the [width] by [height] rectangle with its top-left corner at ([left], [top]) out of
+    /// the row-major [cells] of a grid [grid_width] cells wide. The rectangle must lie within it.
+    pub fn from_region(cells: &[u8], grid_width: usize, left: usize, top: usize, width: usize, height: usize) -> Self {
+        let mut pattern = Pattern::new(width, height);
+        for y in 0..height {
+            let row = (top + y) * grid_width + left;
+            pattern.cells[y * width..(y + 1) * width].copy_from_slice(&cells[row..row + width]);
+        }
+        pattern
+    }
+
+    /// The smallest pattern holding each `(x, y, state)` of [cells], offset to where the top-left
+    /// one lies, or [None] if they span more than [MAX_CELLS].
+    pub fn from_cells(cells: &[(i64, i64, u8)]) -> Option<Self> {
+        let Some(&(x, y, _)) = cells.first() else {
+            return Some(Pattern::default());
+        };
+        let (mut left, mut top, mut right, mut bottom) = (x, y, x, y);
+        for &(x, y, _) in cells {
+            (left, top) = (left.min(x), top.min(y));
+            (right, bottom) = (right.max(x), bottom.max(y));
+        }
+        let width = usize::try_from(right.checked_sub(left)?.checked_add(1)?).ok()?;
+        let height = usize::try_from(bottom.checked_sub(top)?.checked_add(1)?).ok()?;
+        width.checked_mul(height).filter(|&cells| cells <= MAX_CELLS)?;
+
+        let mut pattern = Pattern::new(width, height);
+        for &(x, y, state) in cells {
+            pattern.cells[(y - top) as usize * width + (x - left) as usize] = state;
+        }
+        pattern.offset = Some((left, top));
+        Some(pattern)
+    }
+
+    /// Copies the whole of [grid].
+    pub fn from_grid(grid: &Grid) -> Self {
+        Pattern::from_region(&grid.cells, grid.width, 0, 0, grid.width, grid.height)
+    }
+
+    /// The same pattern cut down to its [Pattern::bounding_box], moving the offset (if any) to
+    /// match. Empty patterns shrink to nothing.
+    pub fn trimmed(&self) -> Self {
+        let (left, top, width, height) = self.bounding_box().unwrap_or((0, 0, 0, 0));
+        let cells = Pattern::from_region(&self.cells, self.width, left, top, width, height).cells;
+        let offset = self.offset.map(|(x, y)| (x + left as i64, y + top as i64));
+        Pattern { offset, ..self.with_cells(cells, width, height) }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * self.width + x]
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell != 0).count()
+    }
+
+    /// The smallest rectangle `(left, top, width, height)` containing every live cell, which may be
+    /// smaller than the one the file declared, or [None] if the pattern is empty.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let live = |x: usize, y: usize| self.get(x, y) != 0;
+        let rows: Vec<usize> = (0..self.height).filter(|&y| (0..self.width).any(|x| live(x, y))).collect();
+        let columns: Vec<usize> = (0..self.width).filter(|&x| (0..self.height).any(|y| live(x, y))).collect();
+        let (&top, &bottom) = (rows.first()?, rows.last()?);
+        let (&left, &right) = (columns.first()?, columns.last()?);
+        Some((left, top, right - left + 1, bottom - top + 1))
+    }
+
+    /// Writes the pattern into [grid] with its top-left corner at ([left], [top]), which may lie
+    /// off the grid. Only live cells are written, so whatever is already there shows through.
+    pub fn place(&self, grid: &mut Grid, left: i64, top: i64, oversize: Oversize) -> Result<(), TooLarge> {
+        if oversize == Oversize::Reject && (self.width > grid.width || self.height > grid.height) {
+            return Err(TooLarge { pattern: (self.width, self.height), grid: (grid.width, grid.height) });
+        }
+
+        for y in 0..self.height {
+            let gy = top + y as i64;
+            if !(0..grid.height as i64).contains(&gy) {
+                continue;
+            }
+            for x in 0..self.width {
+                let gx = left + x as i64;
+                let cell = self.get(x, y);
+                if cell != 0 && (0..grid.width as i64).contains(&gx) {
+                    grid.cells[gy as usize * grid.width + gx as usize] = cell;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the pattern into the middle of [grid], see [Pattern::place].
+    pub fn place_centred(&self, grid: &mut Grid, oversize: Oversize) -> Result<(), TooLarge> {
+        let (left, top) = Anchor::Centre.position(self, grid.width, grid.height);
+        self.place(grid, left, top, oversize)
+    }
+
+    /// Writes the pattern into [grid] as [placement] says, see [Pattern::place]. Tiled copies
+    /// start from where the pattern would otherwise go, and are cropped at the edges of the grid.
+    pub fn place_with(&self, grid: &mut Grid, placement: &Placement) -> Result<(), TooLarge> {
+        let pattern = match placement.flip {
+            Some(flip) => self.flipped(flip),
+            None => self.clone(),
+        };
+        let pattern = pattern.rotated(placement.quarter_turns);
+        let (left, top) = placement.anchor.position(&pattern, grid.width, grid.height);
+        let (left, top) = (left + placement.at.0, top + placement.at.1);
+
+        let Some(gap) = placement.tile else {
+            return pattern.place(grid, left, top, placement.oversize);
+        };
+        if placement.oversize == Oversize::Reject {
+            pattern.place(grid, left, top, placement.oversize)?;
+        }
+        let (across, down) = (pattern.width + gap, pattern.height + gap);
+        if across == 0 || down == 0 {
+            return Ok(());
+        }
+        let first = |start: i64, period: usize| start.rem_euclid(period as i64) - period as i64;
+        for y in (first(top, down)..grid.height as i64).step_by(down) {
+            for x in (first(left, across)..grid.width as i64).step_by(across) {
+                pattern.place(grid, x, y, Oversize::Crop)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The pattern mirrored by [flip], along with its offset, if any, about the origin.
+    pub fn flipped(&self, flip: Flip) -> Self {
+        let (width, height) = (self.width, self.height);
+        let mut cells = vec![0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let (fx, fy) = match flip {
+                    Flip::LeftRight => (width - 1 - x, y),
+                    Flip::TopBottom => (x, height - 1 - y),
+                };
+                cells[fy * width + fx] = self.get(x, y);
+            }
+        }
+        let offset = self.offset.map(|(left, top)| match flip {
+            Flip::LeftRight => (-(left + width as i64), top),
+            Flip::TopBottom => (left, -(top + height as i64)),
+        });
+        Pattern { offset, ..self.with_cells(cells, width, height) }
+    }
+
+    /// The pattern turned clockwise by [quarter_turns] right angles, along with its offset, if any,
+    /// about the origin.
+    pub fn rotated(&self, quarter_turns: u8) -> Self {
+        let mut pattern = self.clone();
+        for _ in 0..quarter_turns % 4 {
+            let (width, height) = (pattern.width, pattern.height);
+            let mut cells = vec![0; width * height];
+            for y in 0..height {
+                for x in 0..width {
+                    cells[x * height + (height - 1 - y)] = pattern.get(x, y);
+                }
+            }
+            let offset = pattern.offset.map(|(left, top)| (-(top + height as i64), left));
+            pattern = Pattern { offset, ..pattern.with_cells(cells, height, width) };
+        }
+        pattern
+    }
+
+    /// The same metadata with different cells.
+    fn with_cells(&self, cells: Vec<u8>, width: usize, height: usize) -> Self {
+        Pattern {
+            cells,
+            width,
+            height,
+            name: self.name.clone(),
+            author: self.author.clone(),
+            comments: self.comments.clone(),
+            rule: self.rule,
+            offset: self.offset,
+        }
+    }
+}
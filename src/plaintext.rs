@@ -0,0 +1,91 @@
+//! Plaintext patterns (`.cells`), as found on the LifeWiki: `!` comment lines, usually starting
+//! with `!Name:`, then one line per row with `.` for a dead cell and `O` for an alive one. Rows may
+//! stop at their last alive cell, and an empty line is an empty row.
+
+use crate::pattern::{Pattern, ReadError, MAX_CELLS};
+use std::fmt::Write;
+
+/// Parses the text of a plaintext file. `!Name:` and `!Author:` comments fill in the pattern's
+/// name and author, and any other comments its comments. `*` is also read as alive.
+pub fn parse(text: &str) -> Result<Pattern, ReadError> {
+    let mut pattern = Pattern::default();
+    let mut rows = Vec::new();
+    for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim_end())) {
+        let Some(comment) = line.strip_prefix('!') else {
+            rows.push((number, line));
+            continue;
+        };
+
+        let comment = comment.trim();
+        if let Some(name) = comment.strip_prefix("Name:") {
+            pattern.name = Some(name.trim().to_string());
+        } else if let Some(author) = comment.strip_prefix("Author:") {
+            pattern.author = Some(author.trim().to_string());
+        } else {
+            pattern.comments.push(comment.to_string());
+        }
+    }
+    while rows.last().is_some_and(|(_, row)| row.is_empty()) {
+        rows.pop();
+    }
+
+    let width = rows.iter().map(|(_, row)| row.chars().count()).max().unwrap_or(0);
+    let cells = width.checked_mul(rows.len()).filter(|&cells| cells <= MAX_CELLS).ok_or(ReadError::TooLarge)?;
+    (pattern.cells, pattern.width, pattern.height) = (vec![0; cells], width, rows.len());
+    for (y, &(number, row)) in rows.iter().enumerate() {
+        for (x, ch) in row.chars().enumerate() {
+            match ch {
+                '.' => {}
+                'O' | 'o' | '*' => pattern.cells[y * width + x] = 1,
+                found => return Err(ReadError::UnexpectedCharacter { line: number, column: x + 1, found }),
+            }
+        }
+    }
+    Ok(pattern)
+}
+
+/// Encodes [pattern] as plaintext, with its name, author and comments as `!` lines. The format has
+/// only two states, so cells in any state but dead are written alive, and the rule is left out.
+pub fn encode(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(out, "!Name: {name}").unwrap();
+    }
+    if let Some(author) = &pattern.author {
+        writeln!(out, "!Author: {author}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(out, "!{comment}").unwrap();
+    }
+    for row in pattern.cells.chunks(pattern.width.max(1)) {
+        out.extend(row.iter().map(|&cell| if cell == 0 { '.' } else { 'O' }));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_encode() {
+        let mut pattern = Pattern::new(4, 3);
+        pattern.cells = vec![0, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 1];
+        pattern.name = Some("Scattered".to_string());
+        pattern.author = Some("Nobody".to_string());
+        pattern.comments = vec!["Three rows".to_string()];
+        assert_eq!(parse(&encode(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn rejects_patterns_too_large_to_allocate() {
+        let text = format!("{}{}O", "O".repeat(40_000), "\n".repeat(30_000));
+        assert!(matches!(parse(&text), Err(ReadError::TooLarge)));
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        assert!(matches!(parse("!Name: Bad\n.O\nOx"), Err(ReadError::UnexpectedCharacter { line: 3, column: 2, found: 'x' })));
+    }
+}
@@ -1,98 +1,294 @@
-/// Run-length-encoding (RLE) to load custom seeds.
-///
-/// This entire file was essentially written by ChatGPT. Teehee.
-
-use std::fs;
-use std::io;
-use std::path::PathBuf;
-
-
-/// Writes an RLE pattern into `grid` (row-major 1D), centered within (width,height).
-/// Assumes `grid.len() == width * height`.
-pub fn decode_rle_into_centered(
-    path: PathBuf,
-    grid: &mut Vec<u8>,
-    width: usize,
-    height: usize,
-) -> io::Result<()> {
-    let text = fs::read_to_string(path)?;
-    grid.fill(0);
-
-    // ---- extract header + payload ----
-    let mut header: Option<String> = None;
-    let mut payload = String::new();
-
-    for line in text.lines() {
+//! Run-length encoded (RLE) patterns, the usual format for sharing patterns, as used by Golly and
+//! the LifeWiki. A file holds `#` comment lines, then a header such as
+//! `x = 3, y = 3, rule = B3/S23` giving the bounding box, then the cells row by row: `b` (or `.`)
+//! is dead, `o` alive, `A` to `X` states 1 to 24 (prefixed by `p` to `y` for further blocks of 24),
+//! `$` ends a row, and `!` the pattern. Each of these may be preceded by a repeat count.
+
+use crate::pattern::{Pattern, ReadError, MAX_CELLS};
+use crate::rule::Rule;
+use std::fmt::Write;
+
+/// Parses the text of an RLE file. Comment lines fill in the pattern's metadata: `#N` its name,
+/// `#O` its author, `#C` (or `#c`) its comments, `#R` (or `#P`) its offset and `#r` its rule, if
+/// the header has none. Golly's bounded grid suffix on a rule, as in `B3/S23:T100,100`, is
+/// dropped. Anything after the closing `!` is ignored.
+pub fn parse(text: &str) -> Result<Pattern, ReadError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let mut pattern = Pattern::default();
+    let mut comment_rule = None;
+
+    let (header_line, header) = loop {
+        let Some((number, line)) = lines.next() else {
+            return Err(ReadError::MissingHeader { expected: "x = .., y = .." });
+        };
         let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
+        let Some(comment) = line.strip_prefix('#') else {
+            if line.is_empty() {
+                continue;
+            }
+            break (number, line);
+        };
+
+        let (kind, rest) = comment.split_at(comment.chars().next().map_or(0, char::len_utf8));
+        let rest = rest.trim();
+        match kind {
+            "N" => pattern.name = Some(rest.to_string()),
+            "O" => pattern.author = Some(rest.to_string()),
+            "C" | "c" => pattern.comments.push(rest.to_string()),
+            "R" | "P" => {
+                let coordinates: Vec<i64> = rest.split_whitespace().map_while(|value| value.parse().ok()).collect();
+                let [x, y] = coordinates[..] else {
+                    return Err(ReadError::InvalidCoordinates { line: number });
+                };
+                pattern.offset = Some((x, y));
+            }
+            "r" => comment_rule = Some((number, rest)),
+            _ => {}
         }
-        if header.is_none() && line.contains('x') && line.contains('y') && line.contains('=') {
-            header = Some(line.to_owned());
-            continue;
+    };
+
+    let (width, height, rule) = parse_header(header, header_line)?;
+    let cells = width.checked_mul(height).filter(|&cells| cells <= MAX_CELLS).ok_or_else(|| ReadError::InvalidHeader {
+        line: header_line,
+        message: format!("a {width}x{height} pattern is larger than the limit of {MAX_CELLS} cells"),
+    })?;
+    (pattern.cells, pattern.width, pattern.height) = (vec![0; cells], width, height);
+    if let Some((line, rule)) = rule.map(|rule| (header_line, rule)).or(comment_rule) {
+        let rule = without_bounded_grid(rule);
+        let rule = rule.parse::<Rule>().map_err(|message| ReadError::InvalidRule { line, message })?;
+        pattern.rule = Some(rule);
+    }
+
+    let (mut x, mut y, mut run) = (0usize, 0usize, None::<usize>);
+    let mut prefix: Option<u8> = None;
+    let mut prefix_at = (0, 0);
+    for (number, line) in lines {
+        for (column, ch) in line.chars().enumerate().map(|(i, ch)| (i + 1, ch)) {
+            let invalid_state = ReadError::InvalidState { line: number, column };
+            let state = match (prefix.take(), ch) {
+                (None, '0'..='9') => {
+                    let digit = ch as usize - '0' as usize;
+                    run = Some(run.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    continue;
+                }
+                (None, 'p'..='y') => {
+                    prefix = Some(ch as u8 - b'p' + 1);
+                    prefix_at = (number, column);
+                    continue;
+                }
+                (None, 'b' | '.') => 0,
+                (None, 'o') => 1,
+                (blocks, 'A'..='X') => {
+                    let state = blocks.unwrap_or(0) as usize * 24 + (ch as u8 - b'A' + 1) as usize;
+                    match u8::try_from(state) {
+                        Ok(state) if pattern.rule.is_none_or(|rule| state < rule.states()) => state,
+                        _ => return Err(invalid_state),
+                    }
+                }
+                (Some(_), _) => return Err(invalid_state),
+                (None, '$') => {
+                    (x, y) = (0, y.saturating_add(run.take().unwrap_or(1)));
+                    continue;
+                }
+                (None, '!') => return Ok(pattern),
+                (None, ch) if ch.is_whitespace() => continue,
+                (None, found) => return Err(ReadError::UnexpectedCharacter { line: number, column, found }),
+            };
+
+            let count = run.take().unwrap_or(1);
+            if state != 0 {
+                if y >= height || x.saturating_add(count) > width {
+                    return Err(ReadError::OutOfBounds { line: number, column });
+                }
+                pattern.cells[y * width + x..y * width + x + count].fill(state);
+            }
+            x = x.saturating_add(count);
         }
-        payload.push_str(line);
     }
+    match prefix {
+        Some(_) => Err(ReadError::InvalidState { line: prefix_at.0, column: prefix_at.1 }),
+        None => Ok(pattern),
+    }
+}
 
-    let header = header.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing RLE header"))?;
-    let (pat_w, pat_h) = parse_rle_header_xy(&header)
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid RLE header"))?;
-    assert!(pat_w <= width && pat_h <= height, "pattern too big for target grid");
+/// The longest line [encode] writes, as recommended by the format.
+const LINE_LENGTH: usize = 70;
 
-    // Center the pattern's bounding box within the target grid.
-    let ox: isize = (width as isize) / 2 - (pat_w as isize) / 2;
-    let oy: isize = (height as isize) / 2 - (pat_h as isize) / 2;
+/// Encodes [pattern] as RLE, with its metadata as comment lines. Dead cells at the ends of rows and
+/// empty rows at the bottom are left out, and lines are wrapped at 70 columns without splitting a
+/// run. Patterns with more than two states, or a rule that has more, use the multi-state letters.
+pub fn encode(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    for (kind, text) in [("N", &pattern.name), ("O", &pattern.author)] {
+        if let Some(text) = text {
+            writeln!(out, "#{kind} {text}").unwrap();
+        }
+    }
+    for comment in &pattern.comments {
+        writeln!(out, "#C {comment}").unwrap();
+    }
+    if let Some((x, y)) = pattern.offset {
+        writeln!(out, "#R {x} {y}").unwrap();
+    }
+    write!(out, "x = {}, y = {}", pattern.width, pattern.height).unwrap();
+    if let Some(rule) = pattern.rule {
+        write!(out, ", rule = {rule}").unwrap();
+    }
+    out.push('\n');
 
-    // ---- decode payload ----
-    let mut x: isize = 0;
-    let mut y: isize = 0;
-    let mut run: usize = 0;
+    let multi_state = pattern.rule.is_some_and(|rule| rule.states() > 2) || pattern.cells.iter().any(|&cell| cell > 1);
+    let mut line = String::new();
+    let mut push = |run: usize, tag: &str| {
+        let token = if run > 1 { format!("{run}{tag}") } else { tag.to_string() };
+        if line.len() + token.len() > LINE_LENGTH {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        line.push_str(&token);
+    };
 
-    for ch in payload.chars() {
-        match ch {
-            '0'..='9' => run = run * 10 + (ch as u8 - b'0') as usize,
+    let mut written = 0;
+    for y in 0..pattern.height {
+        let row = &pattern.cells[y * pattern.width..(y + 1) * pattern.width];
+        let Some(end) = row.iter().rposition(|&cell| cell != 0) else {
+            continue;
+        };
+        if y > written {
+            push(y - written, "$");
+        }
+        written = y;
+        for run in row[..=end].chunk_by(|a, b| a == b) {
+            push(run.len(), &tag(run[0], multi_state));
+        }
+    }
+    push(1, "!");
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
 
-            'b' => {
-                x += run.max(1) as isize;
-                run = 0;
+/// The letters for a cell in [state].
+fn tag(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (0, true) => ".".to_string(),
+        (1, false) => "o".to_string(),
+        _ => {
+            let (block, letter) = ((state - 1) / 24, (b'A' + (state - 1) % 24) as char);
+            match block {
+                0 => letter.to_string(),
+                _ => format!("{}{letter}", (b'p' + block - 1) as char),
             }
+        }
+    }
+}
 
-            'o' => {
-                let n = run.max(1);
-                for _ in 0..n {
-                    let gx = ox + x;
-                    let gy = oy + y;
-                    if (0..width as isize).contains(&gx) && (0..height as isize).contains(&gy) {
-                        grid[gx as usize + width * (gy as usize)] = 1;
-                    }
-                    x += 1;
-                }
-                run = 0;
-            }
+/// Strips a Golly bounded grid suffix, such as the `:T100,100` of a torus or the `:P30,20` of a
+/// plane, from [rule]. The grid's size and topology are left to the command line.
+fn without_bounded_grid(rule: &str) -> &str {
+    match rule.rsplit_once(':') {
+        Some((rule, suffix)) if suffix.trim_start().starts_with(['P', 'T', 'K', 'C', 'S']) => rule.trim_end(),
+        _ => rule,
+    }
+}
 
-            '$' => {
-                y += run.max(1) as isize;
-                x = 0;
-                run = 0;
-            }
+/// Parses a header such as `x = 3, y = 3, rule = B3/S23` into the bounding box and the rule. The
+/// rule comes last, and runs to the end of the line, since it may itself contain commas.
+fn parse_header(header: &str, line: usize) -> Result<(usize, usize, Option<&str>), ReadError> {
+    let invalid = |message: String| ReadError::InvalidHeader { line, message };
+    let (sizes, rule) = match header.find("rule") {
+        Some(i) => {
+            let rule = header[i + "rule".len()..].trim_start().strip_prefix('=');
+            let rule = rule.ok_or_else(|| invalid("expected '=' after 'rule'".to_string()))?.trim();
+            (&header[..i], Some(rule).filter(|rule| !rule.is_empty()))
+        }
+        None => (header, None),
+    };
 
-            '!' => break,
-            _ => {}
+    let (mut width, mut height) = (None, None);
+    for field in sizes.split(',').map(str::trim).filter(|field| !field.is_empty()) {
+        let (key, value) = field.split_once('=').ok_or_else(|| invalid(format!("expected 'key = value', found '{field}'")))?;
+        let value = value.trim().parse::<usize>().map_err(|_| invalid(format!("invalid size '{}'", value.trim())))?;
+        match key.trim() {
+            "x" => width = Some(value),
+            "y" => height = Some(value),
+            key => return Err(invalid(format!("unknown field '{key}'"))),
         }
     }
-
-    Ok(())
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err(invalid("both 'x' and 'y' are required".to_string())),
+    }
 }
 
-fn parse_rle_header_xy(header: &str) -> Option<(usize, usize)> {
-    // Typical: "x = 398, y = 405, rule = B3/S23"
-    fn parse_key_usize(s: &str, key: &str) -> Option<usize> {
-        let i = s.find(key)?;
-        let rest = &s[i + key.len()..];
-        let rest = rest.trim_start();
-        let rest = rest.strip_prefix('=')?.trim_start();
-        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
-        digits.parse().ok()
-    }
-    Some((parse_key_usize(header, "x")?, parse_key_usize(header, "y")?))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_encode() {
+        let mut pattern = Pattern::new(200, 4);
+        for x in (0..200).step_by(3) {
+            pattern.cells[x] = 1;
+        }
+        pattern.cells[3 * 200 + 199] = 1;
+        pattern.name = Some("Dots".to_string());
+        pattern.author = Some("Nobody".to_string());
+        pattern.comments = vec!["First".to_string(), "Second".to_string()];
+        pattern.rule = Some("B3/S23".parse().unwrap());
+        pattern.offset = Some((-100, -2));
+
+        let text = encode(&pattern);
+        assert!(text.lines().all(|line| line.len() <= LINE_LENGTH));
+        assert_eq!(parse(&text).unwrap(), pattern);
+    }
+
+    #[test]
+    fn round_trips_multi_state_patterns() {
+        let mut pattern = Pattern::new(3, 2);
+        pattern.cells = vec![1, 0, 2, 0, 200, 1];
+        assert_eq!(parse(&encode(&pattern)).unwrap(), pattern);
+
+        pattern.cells = vec![1, 0, 2, 0, 2, 1];
+        pattern.rule = Some("B2/S/C3".parse().unwrap());
+        assert_eq!(parse(&encode(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        let error = |text: &str| parse(text).unwrap_err();
+        assert!(matches!(
+            error("#N Test\nx = 3, y = 2\nbo$\n2oz!"),
+            ReadError::UnexpectedCharacter { line: 4, column: 3, found: 'z' }
+        ));
+        assert!(matches!(error("x = 2, y = 1\n3o!"), ReadError::OutOfBounds { line: 2, column: 2 }));
+        assert!(matches!(error("x = 2, y = 1\n$o!"), ReadError::OutOfBounds { line: 2, column: 2 }));
+        assert!(matches!(error("x = 2, y = 1, rule = B3/S23\nAB!"), ReadError::InvalidState { line: 2, column: 2 }));
+        assert!(matches!(error("x = 2, y = 1\npb!"), ReadError::InvalidState { line: 2, column: 2 }));
+        assert!(matches!(error("x = 2, y = 1\noq"), ReadError::InvalidState { line: 2, column: 2 }));
+        assert!(matches!(error("#R 1\nx = 1, y = 1\no!"), ReadError::InvalidCoordinates { line: 1 }));
+        assert!(matches!(error("#C Nothing\n"), ReadError::MissingHeader { .. }));
+        assert!(matches!(error("\nx = 1, y = 1, rule = B9\no!"), ReadError::InvalidRule { line: 2, .. }));
+    }
+
+    #[test]
+    fn ignores_bounded_grid_suffixes_on_rules() {
+        for rule in ["B3/S23:T100,100", "B3/S23:P30,20", "B3/S23:K40*,30", "B3/S23 :T0,50", "B3/S23:S20"] {
+            let pattern = parse(&format!("x = 1, y = 1, rule = {rule}\no!")).unwrap();
+            assert_eq!(pattern.rule, Some(Rule::CONWAY), "{rule}");
+        }
+        let pattern = parse("#r B2/S/C3:T64,64\nx = 1, y = 1\nA!").unwrap();
+        assert_eq!(pattern.rule, Some("B2/S/C3".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_headers_too_large_to_allocate() {
+        let header = |text: &str| match parse(text) {
+            Err(ReadError::InvalidHeader { line, .. }) => line,
+            result => panic!("expected an invalid header, got {result:?}"),
+        };
+        assert_eq!(header("#N Huge\nx = 4000000, y = 4000000\no!"), 2);
+        assert_eq!(header(&format!("x = {}, y = 2\no!", usize::MAX)), 1);
+    }
 }
@@ -0,0 +1,579 @@
+//! Life-like (outer-totalistic) rules, described by the usual B/S rulestrings, their multi-state
+//! Generations extension, isotropic non-totalistic rules in Hensel notation, Larger than Life
+//! rules over extended neighbourhoods, and Golly rule tables.
+
+use crate::isotropic::Transitions;
+use crate::neighbourhood::{parse_range, Neighbourhood};
+use crate::table::{self, RuleTable};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The most disjoint ranges a set of [Counts] can be made of.
+const MAX_INTERVALS: usize = 8;
+
+/// A set of neighbour counts, stored as sorted, disjoint and non-adjacent inclusive ranges. Unused
+/// slots are always `(0, 0)`, so that equal sets compare and hash equal.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Counts {
+    intervals: [(u16, u16); MAX_INTERVALS],
+    len: usize,
+}
+
+impl Counts {
+    /// The counts from [low] to [high] inclusive.
+    const fn range(low: u16, high: u16) -> Self {
+        let mut intervals = [(0, 0); MAX_INTERVALS];
+        intervals[0] = (low, high);
+        Counts { intervals, len: 1 }
+    }
+
+    pub fn contains(&self, count: u16) -> bool {
+        self.intervals().iter().any(|&(low, high)| (low..=high).contains(&count))
+    }
+
+    /// The largest count in the set.
+    pub fn max(&self) -> Option<u16> {
+        self.intervals().last().map(|&(_, high)| high)
+    }
+
+    pub fn intervals(&self) -> &[(u16, u16)] {
+        &self.intervals[..self.len]
+    }
+
+    /// Adds the counts from [low] to [high] inclusive, merging them with any they overlap or touch.
+    fn insert(&mut self, low: u16, high: u16) -> Result<(), String> {
+        let mut intervals = self.intervals().to_vec();
+        intervals.push((low, high));
+        intervals.sort_unstable();
+
+        let mut merged: Vec<(u16, u16)> = Vec::new();
+        for (low, high) in intervals {
+            match merged.last_mut() {
+                Some(last) if low <= last.1.saturating_add(1) => last.1 = last.1.max(high),
+                _ => merged.push((low, high)),
+            }
+        }
+        if merged.len() > MAX_INTERVALS {
+            return Err(format!("Too many separate ranges of counts: at most {MAX_INTERVALS} are supported."));
+        }
+
+        *self = Counts::default();
+        self.intervals[..merged.len()].copy_from_slice(&merged);
+        self.len = merged.len();
+        Ok(())
+    }
+
+    /// Each count in the set, in increasing order.
+    fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.intervals().iter().flat_map(|&(low, high)| low..=high)
+    }
+}
+
+/// A life-like, Generations or Larger than Life rule. A dead (resp. alive) cell is alive in the
+/// next generation when its number of alive neighbours is in [birth] (resp. [survival]).
+///
+/// Cells have [states] states: `0` is dead, `1` is alive, and any further states are refractory.
+/// An alive cell that does not survive enters state `2`, and then ages by one state per generation
+/// until it reaches [states] and dies. Only alive cells count as neighbours, and refractory cells
+/// cannot be born into. Life-like rules are the special case of two states.
+///
+/// Neighbours are counted over [neighbourhood], which is Moore's unless the rulestring ends in `H`
+/// (hexagonal) or `V` (von Neumann), or is a Larger than Life rulestring. Those may also count the
+/// cell itself as one of its neighbours, see [Rule::includes_centre].
+///
+/// Isotropic non-totalistic rules also look at where the neighbours are, through [transitions];
+/// [birth] and [survival] then hold every count with at least one shape enabled.
+///
+/// A rule [table] instead decides every transition itself, and [birth] and [survival] are empty.
+/// Tables are never freed, so that rules stay [Copy], but are shared between rules loading the same
+/// one, see [table::intern].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    birth: Counts,
+    survival: Counts,
+    states: u8,
+    neighbourhood: Neighbourhood,
+    include_centre: bool,
+    transitions: Option<Transitions>,
+    table: Option<&'static RuleTable>,
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub const CONWAY: Rule = Rule {
+        birth: Counts::range(3, 3),
+        survival: Counts::range(2, 3),
+        states: 2,
+        neighbourhood: Neighbourhood::Moore,
+        include_centre: false,
+        transitions: None,
+        table: None,
+    };
+
+    /// The rule given by [table].
+    pub fn from_table(table: &'static RuleTable) -> Self {
+        Rule {
+            birth: Counts::default(),
+            survival: Counts::default(),
+            states: table.states(),
+            neighbourhood: table.neighbourhood(),
+            include_centre: false,
+            transitions: None,
+            table: Some(table),
+        }
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// The same rule, counting neighbours over [neighbourhood] instead. Isotropic non-totalistic
+    /// rules are defined by shapes in the Moore neighbourhood, so cannot be moved to another.
+    pub fn with_neighbourhood(self, neighbourhood: Neighbourhood) -> Result<Self, String> {
+        if let Some(table) = self.table {
+            return Err(format!("The rule table {} defines its own neighbourhood.", table.name()));
+        }
+        if !self.is_totalistic() && neighbourhood != Neighbourhood::Moore {
+            return Err(format!("The isotropic rule {self} only works over the Moore neighbourhood."));
+        }
+        Ok(Rule { neighbourhood, ..self })
+    }
+
+    /// Whether an alive cell counts itself as one of its own neighbours.
+    pub fn includes_centre(&self) -> bool {
+        self.include_centre
+    }
+
+    /// The number of cell states, including dead and alive.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Whether cells are only ever dead or alive.
+    pub fn is_life_like(&self) -> bool {
+        self.states == 2
+    }
+
+    /// Whether the next state of a cell depends only on how many of its neighbours are alive, and
+    /// not on where they are.
+    pub fn is_totalistic(&self) -> bool {
+        self.transitions.is_none() && self.table.is_none()
+    }
+
+    /// The rule table deciding every transition, if the rule was loaded from one.
+    pub fn table(&self) -> Option<&'static RuleTable> {
+        self.table
+    }
+
+    /// Whether a dead cell surrounded by dead cells stays dead, so that empty space stays empty.
+    pub fn is_quiescent(&self) -> bool {
+        match self.table {
+            Some(table) => table.next_state(|_, _| 0) == 0,
+            None => !self.births(0),
+        }
+    }
+
+    /// The configurations of the Moore neighbourhood that are alive in the next generation, for an
+    /// isotropic non-totalistic rule.
+    pub fn transitions(&self) -> Option<&Transitions> {
+        self.transitions.as_ref()
+    }
+
+    /// Whether a dead cell with [neighbours] alive neighbours is born. For isotropic
+    /// non-totalistic rules, whether it may be born, depending on where they are.
+    pub fn births(&self, neighbours: u16) -> bool {
+        self.birth.contains(neighbours)
+    }
+
+    /// Whether an alive cell with [neighbours] alive neighbours survives. For isotropic
+    /// non-totalistic rules, whether it may survive, depending on where they are.
+    pub fn survives(&self, neighbours: u16) -> bool {
+        self.survival.contains(neighbours)
+    }
+
+    /// Whether the rule is written in Larger than Life notation rather than as a B/S rulestring.
+    fn is_larger_than_life(&self) -> bool {
+        self.include_centre
+            || matches!(self.neighbourhood, Neighbourhood::ExtendedMoore(_) | Neighbourhood::ExtendedVonNeumann(_))
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+/// Accepts `B36/S23`, `B36S23`, `S23/B36` and the older survival-first `23/36` notation, plus
+/// Generations rules such as `B2/S345/C4` (or `G4`) and `345/2/4`. Any of these may end in `H` or
+/// `V` to select the hexagonal or von Neumann neighbourhood. Letters after a count, as in `B2-a/S12`,
+/// make the rule isotropic non-totalistic, see [crate::isotropic]. Larger than Life rules are written as
+/// `R5,C0,M1,S34..58,B34..45,NM`, where ranges may also be written `34-58` and listed with commas.
+/// Finally, a built-in rule table may be named, such as `WireWorld`, or a `.rule` file given by path.
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(table) = table::builtin(s) {
+            return Ok(Rule::from_table(table));
+        }
+        if s.ends_with(".rule") {
+            return Ok(Rule::from_table(table::intern(RuleTable::load(Path::new(s))?)));
+        }
+
+        let rule = if s.contains(',') {
+            parse_larger_than_life(s)?
+        } else {
+            let (counts, neighbourhood) = match s.char_indices().last() {
+                Some((i, 'H' | 'h')) => (&s[..i], Neighbourhood::Hexagonal),
+                Some((i, 'V' | 'v')) => (&s[..i], Neighbourhood::VonNeumann),
+                _ => (s, Neighbourhood::Moore),
+            };
+            parse_counts_and_states(counts)?.with_neighbourhood(neighbourhood)?
+        };
+
+        let size = rule.neighbourhood.size() + rule.include_centre as usize;
+        let largest = rule.birth.max().max(rule.survival.max()).unwrap_or(0);
+        if largest as usize > size {
+            return Err(format!("Invalid rule '{s}': the neighbourhood has only {size} cells."));
+        }
+        Ok(rule)
+    }
+}
+
+/// Parses a Larger than Life rulestring, such as `R5,C0,M1,S34..58,B34..45,NM`.
+fn parse_larger_than_life(s: &str) -> Result<Rule, String> {
+    let (mut range, mut states, mut include_centre, mut shape) = (None, 2, false, 'M');
+    let (mut birth, mut survival) = (None, None);
+    let mut target: Option<&mut Option<Counts>> = None;
+
+    for token in s.split(',').map(str::trim) {
+        let mut chars = token.chars();
+        let (letter, value) = match chars.next() {
+            Some(letter) if letter.is_ascii_alphabetic() => (Some(letter.to_ascii_uppercase()), chars.as_str()),
+            _ => (None, token),
+        };
+
+        match letter {
+            Some('R') => range = Some(parse_range(value)?),
+            // Both C0 and C1 mean an ordinary two-state rule.
+            Some('C') => states = if matches!(value, "0" | "1") { 2 } else { parse_states(value)? },
+            Some('M') => {
+                include_centre = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(format!("Invalid rule '{s}': 'M' must be 0 or 1.")),
+                }
+            }
+            Some('N') => shape = value.chars().next().unwrap_or(' ').to_ascii_uppercase(),
+            Some('S') => target = Some(&mut survival),
+            Some('B') => target = Some(&mut birth),
+            Some(_) => return Err(format!("Invalid rule '{s}': unknown section '{token}'.")),
+            None if target.is_none() => {
+                return Err(format!("Invalid rule '{s}': counts must follow 'B' or 'S'."));
+            }
+            None => {}
+        }
+
+        // Counts belong to the most recent 'S' or 'B', which may run over several tokens.
+        if matches!(letter, None | Some('S' | 'B'))
+            && let Some(counts) = target.as_deref_mut()
+        {
+            let counts = counts.get_or_insert_default();
+            if !value.is_empty() {
+                let (low, high) = parse_count_range(value)?;
+                counts.insert(low, high)?;
+            }
+        } else {
+            target = None;
+        }
+    }
+
+    let range = range.ok_or_else(|| format!("Invalid rule '{s}': the range 'R' is required."))?;
+    let neighbourhood = match shape {
+        'M' => Neighbourhood::ExtendedMoore(range),
+        'N' => Neighbourhood::ExtendedVonNeumann(range),
+        _ => return Err(format!("Invalid rule '{s}': the neighbourhood must be 'NM' (Moore) or 'NN' (von Neumann).")),
+    };
+    match (birth, survival) {
+        (Some(birth), Some(survival)) => {
+            Ok(Rule { birth, survival, states, neighbourhood, include_centre, transitions: None, table: None })
+        }
+        _ => Err(format!("Invalid rule '{s}': both 'B' and 'S' sections are required.")),
+    }
+}
+
+/// Parses a single count such as `34`, or a range of counts such as `34..58` or `34-58`.
+fn parse_count_range(s: &str) -> Result<(u16, u16), String> {
+    let parse = |count: &str| count.trim().parse::<u16>().map_err(|_| format!("Invalid neighbour count '{count}'."));
+    let (low, high) = match s.split_once("..").or_else(|| s.split_once('-')) {
+        Some((low, high)) => (parse(low)?, parse(high)?),
+        None => (parse(s)?, parse(s)?),
+    };
+    if low > high {
+        return Err(format!("Invalid range of counts '{s}': the ends are the wrong way round."));
+    }
+    Ok((low, high))
+}
+
+/// Parses a rulestring without its neighbourhood suffix.
+fn parse_counts_and_states(s: &str) -> Result<Rule, String> {
+    if s.contains(['B', 'b', 'S', 's']) {
+        return parse_prefixed(s);
+    }
+
+    let sections: Vec<&str> = s.split('/').collect();
+    let (survival, birth, states) = match sections[..] {
+        [survival, birth] => (survival, birth, None),
+        [survival, birth, states] => (survival, birth, Some(states)),
+        _ => return Err(format!("Invalid rule '{s}': expected 'B../S..', '../..' or '../../..'.")),
+    };
+    Ok(Rule {
+        birth: parse_counts(birth)?,
+        survival: parse_counts(survival)?,
+        states: states.map_or(Ok(2), parse_states)?,
+        neighbourhood: Neighbourhood::Moore,
+        include_centre: false,
+        transitions: None,
+        table: None,
+    })
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(table) = self.table {
+            return write!(f, "{}", table.name());
+        }
+        if self.is_larger_than_life() {
+            let ranges = |counts: &Counts| {
+                let ranges: Vec<String> = counts.intervals().iter().map(|(low, high)| format!("{low}..{high}")).collect();
+                ranges.join(",")
+            };
+            let (range, shape) = match self.neighbourhood {
+                Neighbourhood::ExtendedVonNeumann(range) => (range as usize, 'N'),
+                neighbourhood => (neighbourhood.range(), 'M'),
+            };
+            let states = if self.is_life_like() { 0 } else { self.states };
+            let centre = self.include_centre as u8;
+            let (survival, birth) = (ranges(&self.survival), ranges(&self.birth));
+            return write!(f, "R{range},C{states},M{centre},S{survival},B{birth},N{shape}");
+        }
+
+        match &self.transitions {
+            Some(transitions) => {
+                write!(f, "B{}/S{}", transitions.format_section(false), transitions.format_section(true))?
+            }
+            None => {
+                let digits = |counts: &Counts| counts.iter().map(|n| n.to_string()).collect::<String>();
+                write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survival))?
+            }
+        }
+        if !self.is_life_like() {
+            write!(f, "/C{}", self.states)?;
+        }
+        match self.neighbourhood {
+            Neighbourhood::Hexagonal => write!(f, "H"),
+            Neighbourhood::VonNeumann => write!(f, "V"),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn parse_prefixed(s: &str) -> Result<Rule, String> {
+    // Each section is introduced by its letter, and collects the digits (and any Hensel letters)
+    // that follow it.
+    let (mut birth, mut survival, mut states) = (None, None, None);
+    let mut target: Option<&mut Option<String>> = None;
+    let mut after_count = false;
+
+    for ch in s.chars() {
+        // Straight after a count, a lowercase 'c' is a Hensel letter rather than the states section.
+        let is_letter = ch == 'c' && after_count;
+        after_count = false;
+        match ch {
+            'B' | 'b' => target = Some(&mut birth),
+            'S' | 's' => target = Some(&mut survival),
+            'C' | 'c' | 'G' | 'g' if !is_letter => target = Some(&mut states),
+            '/' => continue,
+            _ => {
+                let Some(section) = target.as_deref_mut() else {
+                    return Err(format!("Invalid rule '{s}': counts must follow 'B', 'S' or 'C'."));
+                };
+                section.get_or_insert_default().push(ch);
+                after_count = true;
+                continue;
+            }
+        }
+
+        // A freshly selected section may legitimately stay empty, as in Seeds' `B2/S`.
+        if let Some(section) = target.as_deref_mut() {
+            section.get_or_insert_default();
+        }
+    }
+
+    let (Some(birth), Some(survival)) = (birth, survival) else {
+        return Err(format!("Invalid rule '{s}': both 'B' and 'S' sections are required."));
+    };
+    let states = states.as_deref().map_or(Ok(2), parse_states)?;
+    if birth.chars().chain(survival.chars()).all(|ch| ch.is_ascii_digit()) {
+        return Ok(Rule {
+            birth: parse_counts(&birth)?,
+            survival: parse_counts(&survival)?,
+            states,
+            neighbourhood: Neighbourhood::Moore,
+            include_centre: false,
+            transitions: None,
+            table: None,
+        });
+    }
+
+    let transitions = Transitions::parse(&birth, &survival)?;
+    let counts = |centre: bool| {
+        (0..=8).filter(|&n| transitions.any_with(n, centre)).try_fold(Counts::default(), |mut counts, n| {
+            counts.insert(n as u16, n as u16).map(|_| counts)
+        })
+    };
+    Ok(Rule {
+        birth: counts(false)?,
+        survival: counts(true)?,
+        states,
+        neighbourhood: Neighbourhood::Moore,
+        include_centre: false,
+        // Listing every shape of a count, as in `B3aceijknqry`, is the same as the bare count.
+        transitions: Some(transitions).filter(|transitions| !transitions.is_totalistic()),
+        table: None,
+    })
+}
+
+/// Parses the number of states of a Generations rule.
+fn parse_states(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(states) if states >= 2 => Ok(states),
+        _ => Err(format!("Invalid number of states '{s}': expected a number from 2 to 255.")),
+    }
+}
+
+/// Parses a run of neighbour counts such as `236`.
+fn parse_counts(s: &str) -> Result<Counts, String> {
+    s.chars().try_fold(Counts::default(), |mut counts, ch| match ch.to_digit(10) {
+        Some(n) if n <= 8 => counts.insert(n as u16, n as u16).map(|_| counts),
+        _ => Err(format!("Invalid neighbour count '{ch}': expected a digit from 0 to 8.")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Rule {
+        s.parse().unwrap_or_else(|e| panic!("{s}: {e}"))
+    }
+
+    #[test]
+    fn parses_birth_and_survival_in_either_order() {
+        for s in ["B3/S23", "B3S23", "b3/s23", "S23/B3", "23/3", " B3/S23 "] {
+            assert_eq!(parse(s), Rule::CONWAY, "{s}");
+        }
+        assert_eq!(parse("B36/S23"), parse("23/36"));
+        assert_eq!(parse("B2/S"), parse("/2"));
+        assert!(parse("B2/S").births(2) && !parse("B2/S").survives(2));
+    }
+
+    #[test]
+    fn rejects_malformed_rulestrings() {
+        for s in [
+            "",
+            "B3",
+            "S23",
+            "3",
+            "2/3/4/5",
+            "B9/S23",
+            "B3/S2x",
+            "3B/S23",
+            "B3/S23/C1",
+            "B3/S23/C256",
+            "B7/S23H",
+            "B5/S23V",
+            "B2-z/S23",
+            "R5,C0,M1,S34..58",
+            "R5,C0,M2,S34..58,B34..45,NM",
+            "R5,C0,M1,S58..34,B34..45,NM",
+            "R5,C0,M1,S34..58,B34..45,NX",
+            "R1,C0,M0,S2..3,B10,NM",
+            "C0,M1,S34..58,B34..45,NM",
+            "nonexistent.rule",
+        ] {
+            assert!(s.parse::<Rule>().is_err(), "{s} should not parse");
+        }
+    }
+
+    #[test]
+    fn parses_larger_than_life_rules() {
+        let bosco = parse("R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(bosco.neighbourhood(), Neighbourhood::ExtendedMoore(5));
+        assert!(bosco.includes_centre() && bosco.is_life_like());
+        assert!(bosco.survives(34) && bosco.survives(58) && !bosco.survives(33) && !bosco.survives(59));
+        assert!(bosco.births(34) && bosco.births(45) && !bosco.births(46));
+        assert_eq!(parse("r5, c1, m1, s34-58, b34-45, nm"), bosco);
+
+        let diamond = parse("R3,C4,M0,S2..3,5,B3,7..9,NN");
+        assert_eq!(diamond.neighbourhood(), Neighbourhood::ExtendedVonNeumann(3));
+        assert_eq!(diamond.states(), 4);
+        assert!(!diamond.includes_centre());
+        let survives: Vec<u16> = (0..=25).filter(|&n| diamond.survives(n)).collect();
+        let births: Vec<u16> = (0..=25).filter(|&n| diamond.births(n)).collect();
+        assert_eq!((survives, births), (vec![2, 3, 5], vec![3, 7, 8, 9]));
+    }
+
+    #[test]
+    fn displays_rules_in_canonical_form() {
+        for (s, canonical) in [
+            ("23/3", "B3/S23"),
+            ("S23/B36", "B36/S23"),
+            ("345/2/4", "B2/S345/C4"),
+            ("B2/S345/G4", "B2/S345/C4"),
+            ("B2/S34h", "B2/S34H"),
+            ("B2/S/C3V", "B2/S/C3V"),
+            ("B3aceijknqry/S23", "B3/S23"),
+            ("R5,C0,M1,S34-58,B34-45,NM", "R5,C0,M1,S34..58,B34..45,NM"),
+            ("R5,C1,M1,S34..58,B34..45,NM", "R5,C0,M1,S34..58,B34..45,NM"),
+            ("wireworld", "WireWorld"),
+        ] {
+            assert_eq!(parse(s).to_string(), canonical, "{s}");
+        }
+    }
+
+    #[test]
+    fn displayed_rules_parse_back_to_themselves() {
+        for s in [
+            "B3/S23",
+            "B36/S23",
+            "B2/S",
+            "B0123478/S34678",
+            // Generations.
+            "B2/S345/C4",
+            "B2/S/C3",
+            "B34/S34/C255",
+            // Neighbourhood suffixes.
+            "B2/S34H",
+            "B2/S013V",
+            "B2/S345/C4H",
+            // Larger than Life.
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R2,C3,M0,S1..2,4..6,B3,NN",
+            "R10,C0,M1,S123..212,B123..170,NM",
+            // Isotropic non-totalistic.
+            "B2-a/S12",
+            "B2ci3ai4c8/S02ae3eijkq4iz5ar6i7e",
+            "B3/S2-i34q",
+            "B2e3-ckq/S23/C3",
+            // Rule tables.
+            "WireWorld",
+        ] {
+            let rule = parse(s);
+            assert_eq!(parse(&rule.to_string()), rule, "{s} displays as {rule}");
+        }
+    }
+}
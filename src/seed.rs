@@ -1,7 +1,11 @@
-//! Various seed patterns for the Game of Life.
+//! Various seed patterns for the Game of Life, and random soups.
 
-use crate::wrap;
+use crate::boundary::wrap;
+use crate::pattern::Pattern;
+use clap::ValueEnum;
 
+/// A glider in the middle of the grid, and an R-pentomino 60 cells left of and 20 cells above it,
+/// wrapping around the edges if the grid is too small.
 pub fn seed(grid: &mut [u8], width: usize, height: usize) {
     for y in 0..height {
         for x in 0..width {
@@ -16,14 +20,14 @@ pub fn seed(grid: &mut [u8], width: usize, height: usize) {
     for (dx, dy) in glider {
         let x = wrap(cx + dx, width);
         let y = wrap(cy + dy, height);
-        grid[y + width * x] = 1;
+        grid[x + width * y] = 1;
     }
 
     let r_pentomino = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)];
     for (dx, dy) in r_pentomino {
         let x = wrap(cx - 60 + dx, width);
         let y = wrap(cy - 20 + dy, height);
-        grid[y + width * x] = 1;
+        grid[x + width * y] = 1;
     }
 }
 
@@ -62,3 +66,187 @@ pub fn seed_gosper(grid: &mut [u8], width: usize, height: usize) {
         grid[x + width * y] = 1;
     }
 }
+
+/// A random soup: a region in which each cell is alive with the same probability, optionally made
+/// symmetric as in apgsearch. Soups depend only on their fields, so the same soup comes out on
+/// every machine.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Soup {
+    pub width: usize,
+    pub height: usize,
+    /// The chance of each cell being alive, from 0 to 1.
+    pub density: f64,
+    pub symmetry: Symmetry,
+    pub seed: u64,
+}
+
+/// The symmetries of a soup, named as in apgsearch. The region is mirrored or rotated about its
+/// middle, so [Symmetry::C4] and [Symmetry::D8] need a square region.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Symmetry {
+    /// No symmetry.
+    #[default]
+    C1,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn.
+    C4,
+    /// Unchanged by mirroring left to right.
+    D2,
+    /// Unchanged by mirroring left to right or top to bottom.
+    D4,
+    /// Unchanged by any rotation or reflection of the square.
+    D8,
+}
+
+impl Symmetry {
+    /// Whether the symmetry turns the region by a quarter turn, which only fits a square.
+    pub fn needs_square(self) -> bool {
+        matches!(self, Symmetry::C4 | Symmetry::D8)
+    }
+
+    /// Where ([x], [y]) is taken to in a [width] by [height] region by each transformation under
+    /// which the soup is unchanged, including the identity.
+    fn images(self, x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
+        let (mirror_x, mirror_y) = (width - 1 - x, height - 1 - y);
+        match self {
+            Symmetry::C1 => vec![(x, y)],
+            Symmetry::C2 => vec![(x, y), (mirror_x, mirror_y)],
+            Symmetry::C4 => vec![(x, y), (mirror_y, x), (mirror_x, mirror_y), (y, mirror_x)],
+            Symmetry::D2 => vec![(x, y), (mirror_x, y)],
+            Symmetry::D4 => vec![(x, y), (mirror_x, y), (x, mirror_y), (mirror_x, mirror_y)],
+            Symmetry::D8 => vec![
+                (x, y),
+                (mirror_y, x),
+                (mirror_x, mirror_y),
+                (y, mirror_x),
+                (mirror_x, y),
+                (x, mirror_y),
+                (y, x),
+                (mirror_y, mirror_x),
+            ],
+        }
+    }
+}
+
+impl Soup {
+    /// The canonical soup of apgsearch: 16 by 16, half alive and with no symmetry.
+    pub fn new(seed: u64) -> Self {
+        Soup { width: 16, height: 16, density: 0.5, symmetry: Symmetry::C1, seed }
+    }
+
+    /// Draws the soup. One random number is drawn per cell in raster order, and each cell takes
+    /// the number of the first cell it is taken to by the soup's symmetry.
+    pub fn pattern(&self) -> Pattern {
+        assert!(
+            !self.symmetry.needs_square() || self.width == self.height,
+            "{:?} soups must be square!",
+            self.symmetry,
+        );
+        let threshold = (self.density.clamp(0.0, 1.0) * 2f64.powi(64)) as u128;
+        let mut rng = SplitMix64::new(self.seed);
+        let alive: Vec<bool> = (0..self.width * self.height).map(|_| (rng.next_u64() as u128) < threshold).collect();
+
+        let mut pattern = Pattern::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let images = self.symmetry.images(x, y, self.width, self.height);
+                let first = images.into_iter().map(|(x, y)| y * self.width + x).min().unwrap();
+                pattern.cells[y * self.width + x] = alive[first] as u8;
+            }
+        }
+        pattern
+    }
+}
+
+/// SplitMix64, a small random number generator whose output is fixed by its seed alone.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// Turns [text] into a seed: a number is used as it is, and anything else is hashed with 64-bit
+/// FNV-1a, so that soups can be named by any string.
+pub fn parse_seed(text: &str) -> u64 {
+    text.parse().unwrap_or_else(|_| {
+        text.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Flip;
+
+    #[test]
+    fn seeds_a_glider_and_an_r_pentomino() {
+        // Wider than tall, so that mixing up x and y would put cells in the wrong place.
+        let (width, height) = (200, 60);
+        let mut grid = vec![1; width * height];
+        seed(&mut grid, width, height);
+
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| (100 + x, 30 + y));
+        let r_pentomino = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)].map(|(x, y)| (40 + x, 10 + y));
+        let mut expected = [glider, r_pentomino].concat();
+        expected.sort_by_key(|&(x, y)| (y, x));
+        let live: Vec<(usize, usize)> = (0..width * height).filter(|&i| grid[i] == 1).map(|i| (i % width, i / width)).collect();
+        assert_eq!(live, expected);
+    }
+
+    #[test]
+    fn soups_depend_only_on_their_fields() {
+        // The first SplitMix64 output for seed 0, as published with the generator.
+        assert_eq!(SplitMix64::new(0).next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(parse_seed("12345"), 12345);
+        assert_eq!(parse_seed("a"), 0xAF63_DC4C_8601_EC8C);
+
+        let soup = Soup { width: 20, height: 12, density: 0.3, symmetry: Symmetry::D2, seed: parse_seed("soup") };
+        assert_eq!(soup.pattern(), soup.pattern());
+        assert_ne!(soup.pattern(), Soup { seed: parse_seed("soup2"), ..soup }.pattern());
+        assert_eq!(Soup { density: 0.0, ..soup }.pattern().population(), 0);
+        assert_eq!(Soup { density: 1.0, ..soup }.pattern().population(), 20 * 12);
+    }
+
+    #[test]
+    fn soups_are_unchanged_by_their_symmetry() {
+        // Each transformation is a flip followed by a number of quarter turns.
+        let (quarter_turn, half_turn) = ((None, 1), (None, 2));
+        let (left_right, top_bottom) = ((Some(Flip::LeftRight), 0), (Some(Flip::TopBottom), 0));
+        let transform = |pattern: &Pattern, (flip, quarter_turns): (Option<Flip>, u8)| {
+            flip.map_or_else(|| pattern.clone(), |flip| pattern.flipped(flip)).rotated(quarter_turns).cells
+        };
+
+        // The transformations each soup must be unchanged by, and some it should not be.
+        for (symmetry, (width, height), keeps, breaks) in [
+            (Symmetry::C1, (15, 10), &[][..], &[half_turn, left_right][..]),
+            (Symmetry::C2, (15, 10), &[half_turn], &[left_right]),
+            (Symmetry::C4, (16, 16), &[quarter_turn], &[left_right]),
+            (Symmetry::D2, (15, 10), &[left_right], &[top_bottom]),
+            (Symmetry::D4, (15, 10), &[left_right, top_bottom], &[]),
+            (Symmetry::D8, (15, 15), &[quarter_turn, left_right], &[]),
+        ] {
+            for seed in 0..10 {
+                let pattern = Soup { width, height, density: 0.5, symmetry, seed }.pattern();
+                for &kept in keeps {
+                    assert_eq!(transform(&pattern, kept), pattern.cells, "{symmetry:?} soup {seed}");
+                }
+                for &broken in breaks {
+                    assert_ne!(transform(&pattern, broken), pattern.cells, "{symmetry:?} soup {seed}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,523 @@
+//! Golly rule tables: `.rule` files whose `@TABLE` section lists the transitions of an arbitrary
+//! automaton of up to 255 states, and whose optional `@COLORS` section says how to draw them.
+//!
+//! Each transition reads `C,N1,..,Nk,C'`: a cell in state `C` whose neighbours are in states `N1`
+//! to `Nk`, in Golly's clockwise order starting from north, becomes `C'`. Any of the inputs may be
+//! a variable (`var a={0,1,2}`) or an inline set (`{1,2}`); a variable used more than once in a
+//! transition takes the same value each time. The first transition to match a cell wins, and a
+//! cell that matches none stays as it is. `symmetries:` adds the rotations and reflections of every
+//! transition, or with `permute` every ordering of its neighbours.
+
+use crate::neighbourhood::Neighbourhood;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+/// A set of states, with bit `s % 64` of word `s / 64` set when state `s` is in it.
+type States = [u64; 4];
+
+const MOORE: [(isize, isize); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+const VON_NEUMANN: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const HEXAGONAL: [(isize, isize); 6] = [(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)];
+
+/// Brian Silverman's Wireworld: electron heads (1) become tails (2), tails become conductor (3),
+/// and conductor becomes a head when one or two of its neighbours are heads.
+const WIREWORLD: &str = "\
+@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+@COLORS
+0 48 48 48
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+static BUILTIN: LazyLock<[RuleTable; 1]> =
+    LazyLock::new(|| [RuleTable::parse(WIREWORLD).expect("The built-in rule tables are valid")]);
+
+/// The rule table built in under [name], ignoring case. Only `WireWorld` is built in.
+pub fn builtin(name: &str) -> Option<&'static RuleTable> {
+    BUILTIN.iter().find(|table| table.name.eq_ignore_ascii_case(name))
+}
+
+/// Every table handed out by [intern], so that each distinct one is only ever leaked once.
+static INTERNED: LazyLock<Mutex<HashSet<&'static RuleTable>>> = LazyLock::new(Default::default);
+
+/// A copy of [table] that lives for the rest of the program, as rules need. Loading the same table
+/// again, from the same file or another, returns the copy made the first time.
+pub fn intern(table: RuleTable) -> &'static RuleTable {
+    let mut interned = INTERNED.lock().unwrap();
+    if let Some(&table) = interned.get(&table) {
+        return table;
+    }
+    let table = Box::leak(Box::new(table));
+    interned.insert(table);
+    table
+}
+
+/// A compiled rule table. Bit `t % 64` of word `t / 64` of [RuleTable::matches] for position `p`
+/// and state `s` is set when transition `t` accepts state `s` at position `p`, position 0 being
+/// the cell itself and the rest its neighbours in order. A cell's transition is then the first bit
+/// left standing after intersecting the words of each position's state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RuleTable {
+    name: String,
+    states: u8,
+    neighbourhood: Neighbourhood,
+    offsets: &'static [(isize, isize)],
+    matches: Vec<u64>,
+    words: usize,
+    outputs: Vec<u8>,
+    colours: Vec<Option<u32>>,
+}
+
+/// One input of a transition as written, before variables are bound.
+enum Input {
+    States(States),
+    Variable(String),
+}
+
+/// Which rearrangements of a transition's neighbours also apply.
+enum Symmetries {
+    /// Each map sends a neighbour's position to the position it takes.
+    Maps(Vec<Vec<usize>>),
+    Permute,
+}
+
+impl RuleTable {
+    /// Reads and parses the `.rule` file at [path].
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        RuleTable::parse(&text).map_err(|e| format!("Invalid rule table '{}': {e}", path.display()))
+    }
+
+    /// Parses the text of a `.rule` file. Sections other than `@RULE`, `@TABLE` and `@COLORS` are
+    /// ignored, but one of them must be a `@TABLE`: rule trees are not supported.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut name = String::new();
+        let mut section = "";
+        let mut has_table = false;
+        let (mut states, mut offsets, mut symmetries) = (None, &MOORE[..], "none");
+        let mut variables: HashMap<String, States> = HashMap::new();
+        let mut transitions: Vec<(Vec<States>, u8)> = Vec::new();
+        let mut seen: HashSet<Vec<States>> = HashSet::new();
+        let mut colour_lines = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let at = |message: String| format!("line {}: {message}", number + 1);
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('@') {
+                let (keyword, rest) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+                section = keyword;
+                match keyword {
+                    "RULE" => name = rest.trim().to_string(),
+                    "TABLE" => has_table = true,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match section {
+                "TABLE" => {}
+                "COLORS" => {
+                    colour_lines.push((number, line));
+                    continue;
+                }
+                _ => continue,
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                match key.trim() {
+                    "n_states" => match value.parse::<u8>() {
+                        Ok(count) if count >= 2 => states = Some(count),
+                        _ => return Err(at(format!("n_states must be a number from 2 to 255, not '{value}'."))),
+                    },
+                    "neighborhood" => {
+                        offsets = match value {
+                            "Moore" => &MOORE,
+                            "vonNeumann" => &VON_NEUMANN,
+                            "hexagonal" => &HEXAGONAL,
+                            _ => return Err(at(format!("Unsupported neighborhood '{value}'."))),
+                        }
+                    }
+                    "symmetries" => symmetries = value,
+                    key => return Err(at(format!("Unknown setting '{key}'."))),
+                }
+                continue;
+            }
+
+            let states = states.ok_or_else(|| at("n_states must come before any variable or transition.".to_string()))?;
+            if let Some(declaration) = line.strip_prefix("var ") {
+                let (variable, values) = declaration
+                    .split_once('=')
+                    .ok_or_else(|| at(format!("Expected 'var name={{..}}', found '{line}'.")))?;
+                let values = parse_set(values.trim(), states, &variables).map_err(at)?;
+                variables.insert(variable.trim().to_string(), values);
+                continue;
+            }
+
+            let symmetries = parse_symmetries(symmetries, offsets.len()).map_err(at)?;
+            let expanded = expand(line, states, offsets.len(), &variables, &symmetries).map_err(at)?;
+            for (inputs, output) in expanded {
+                // A transition identical to an earlier one can never be the first to match.
+                if seen.insert(inputs.clone()) {
+                    transitions.push((inputs, output));
+                }
+            }
+        }
+
+        if !has_table {
+            return Err("There is no @TABLE section; rule trees are not supported.".to_string());
+        }
+        let states = states.ok_or("The table does not set n_states.")?;
+        let neighbourhood = match offsets.len() {
+            8 => Neighbourhood::Moore,
+            4 => Neighbourhood::VonNeumann,
+            _ => Neighbourhood::Hexagonal,
+        };
+        let colours = parse_colours(&colour_lines, states)?;
+
+        let words = transitions.len().div_ceil(64).max(1);
+        let positions = offsets.len() + 1;
+        let mut matches = vec![0u64; positions * states as usize * words];
+        for (t, (inputs, _)) in transitions.iter().enumerate() {
+            for (position, set) in inputs.iter().enumerate() {
+                for state in 0..states as usize {
+                    if contains(set, state as u8) {
+                        matches[(position * states as usize + state) * words + t / 64] |= 1 << (t % 64);
+                    }
+                }
+            }
+        }
+        let outputs = transitions.into_iter().map(|(_, output)| output).collect();
+
+        Ok(RuleTable { name, states, neighbourhood, offsets, matches, words, outputs, colours })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of cell states, including the dead state `0`.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// The colour of [state] given by the `@COLORS` section, as `0xRRGGBB`.
+    pub fn colour(&self, state: u8) -> Option<u32> {
+        self.colours.get(state as usize).copied().flatten()
+    }
+
+    /// The next state of a cell, where [state_at] gives the state of the cell at an offset from it.
+    /// Cells in states the table does not have match no transition.
+    pub fn next_state(&self, state_at: impl Fn(isize, isize) -> u8) -> u8 {
+        let centre = state_at(0, 0);
+        let mut cells = [centre; 9];
+        for (cell, &(dx, dy)) in cells[1..].iter_mut().zip(self.offsets) {
+            *cell = state_at(dx, dy);
+        }
+        let cells = &cells[..self.offsets.len() + 1];
+        if cells.iter().any(|&state| state >= self.states) {
+            return centre;
+        }
+
+        let states = self.states as usize;
+        for word in 0..self.words {
+            let matching = cells.iter().enumerate().fold(u64::MAX, |acc, (position, &state)| {
+                acc & self.matches[(position * states + state as usize) * self.words + word]
+            });
+            if matching != 0 {
+                return self.outputs[word * 64 + matching.trailing_zeros() as usize];
+            }
+        }
+        centre
+    }
+}
+
+fn contains(set: &States, state: u8) -> bool {
+    set[state as usize / 64] & 1 << (state % 64) != 0
+}
+
+fn single(state: u8) -> States {
+    let mut set = [0; 4];
+    set[state as usize / 64] |= 1 << (state % 64);
+    set
+}
+
+/// Parses a state, a variable's name or a braced list of either, such as `{0,a,3}`.
+fn parse_set(s: &str, states: u8, variables: &HashMap<String, States>) -> Result<States, String> {
+    let Some(list) = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) else {
+        if let Some(&values) = variables.get(s) {
+            return Ok(values);
+        }
+        return match s.parse::<u8>() {
+            Ok(state) if state < states => Ok(single(state)),
+            _ => Err(format!("'{s}' is neither a state below {states} nor a variable.")),
+        };
+    };
+    list.split(',').try_fold([0; 4], |acc, item| {
+        let values = parse_set(item.trim(), states, variables)?;
+        Ok([0, 1, 2, 3].map(|i| acc[i] | values[i]))
+    })
+}
+
+/// The symmetries named by a `symmetries:` setting, for a ring of [neighbours] neighbours:
+/// `none`, `rotateK` for the K-fold rotations, `rotateKreflect` for those and their mirror images,
+/// `reflect_horizontal` or `permute`.
+fn parse_symmetries(name: &str, neighbours: usize) -> Result<Symmetries, String> {
+    let (folds, reflect) = match name {
+        "permute" => return Ok(Symmetries::Permute),
+        "none" => (1, false),
+        "reflect_horizontal" => (1, true),
+        _ => {
+            let rotations = name.strip_prefix("rotate").ok_or_else(|| format!("Unknown symmetries '{name}'."))?;
+            let (folds, reflect) = match rotations.strip_suffix("reflect") {
+                Some(folds) => (folds, true),
+                None => (rotations, false),
+            };
+            match folds.parse::<usize>() {
+                Ok(folds) if folds >= 2 && neighbours.is_multiple_of(folds) => (folds, reflect),
+                _ => return Err(format!("Unsupported symmetries '{name}' for {neighbours} neighbours.")),
+            }
+        }
+    };
+
+    // Neighbours are listed clockwise from north, so rotating shifts them around the ring, and
+    // mirroring left to right reverses it.
+    let step = neighbours / folds;
+    let maps = (0..neighbours)
+        .step_by(step)
+        .flat_map(|shift| {
+            let rotate = move |i: usize| (i + shift) % neighbours;
+            let mirrored = (0..neighbours).map(move |i| rotate((neighbours - i) % neighbours)).collect();
+            let rotated = (0..neighbours).map(rotate).collect();
+            if reflect { vec![rotated, mirrored] } else { vec![rotated] }
+        })
+        .collect();
+    Ok(Symmetries::Maps(maps))
+}
+
+/// Expands one transition line into concrete transitions: each binding of its repeated variables,
+/// under each of the [symmetries].
+fn expand(
+    line: &str,
+    states: u8,
+    neighbours: usize,
+    variables: &HashMap<String, States>,
+    symmetries: &Symmetries,
+) -> Result<Vec<(Vec<States>, u8)>, String> {
+    let tokens = split_tokens(line, states, neighbours);
+    if tokens.len() != neighbours + 2 {
+        return Err(format!("Expected {} entries, found {} in '{line}'.", neighbours + 2, tokens.len()));
+    }
+
+    let inputs = tokens[..=neighbours]
+        .iter()
+        .map(|token| match variables.get(*token) {
+            Some(_) => Ok(Input::Variable(token.to_string())),
+            None => parse_set(token, states, variables).map(Input::States),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let output = tokens[neighbours + 1];
+
+    // Variables used more than once, or named as the output, are bound to one value at a time.
+    let mut bound: Vec<&str> = Vec::new();
+    for (i, input) in inputs.iter().enumerate() {
+        if let Input::Variable(name) = input {
+            let repeated = inputs[i + 1..].iter().any(|other| matches!(other, Input::Variable(o) if o == name));
+            if (repeated || name == output) && !bound.contains(&name.as_str()) {
+                bound.push(name);
+            }
+        }
+    }
+    if !bound.contains(&output) && output.parse::<u8>().map_or(true, |state| state >= states) {
+        return Err(format!("The output '{output}' must be a state below {states}, or a variable used as an input."));
+    }
+
+    let mut bindings: Vec<Vec<u8>> = vec![Vec::new()];
+    for name in &bound {
+        let values: Vec<u8> = (0..states).filter(|&state| contains(&variables[*name], state)).collect();
+        bindings = bindings
+            .into_iter()
+            .flat_map(|binding| values.iter().map(move |&value| [binding.clone(), vec![value]].concat()))
+            .collect();
+    }
+
+    let mut expanded = Vec::new();
+    for binding in bindings {
+        let value_of = |name: &str| bound.iter().position(|&b| b == name).map(|i| binding[i]);
+        let sets: Vec<States> = inputs
+            .iter()
+            .map(|input| match input {
+                Input::States(set) => *set,
+                Input::Variable(name) => value_of(name).map_or(variables[name], single),
+            })
+            .collect();
+        let output = value_of(output).unwrap_or_else(|| output.parse().unwrap());
+
+        let (centre, around) = (sets[0], &sets[1..]);
+        let arrangements: Vec<Vec<States>> = match symmetries {
+            Symmetries::Maps(maps) => maps.iter().map(|map| map.iter().map(|&from| around[from]).collect()).collect(),
+            Symmetries::Permute => permutations(around.to_vec()),
+        };
+        expanded.extend(arrangements.into_iter().map(|around| ([vec![centre], around].concat(), output)));
+    }
+    Ok(expanded)
+}
+
+/// Splits a transition into its entries: comma-separated, except for braced sets, or one character
+/// per entry when a table with at most 10 states writes them without commas.
+fn split_tokens(line: &str, states: u8, neighbours: usize) -> Vec<&str> {
+    if !line.contains(',') && states <= 10 && line.len() == neighbours + 2 {
+        return (0..line.len()).map(|i| &line[i..i + 1]).collect();
+    }
+    let (mut tokens, mut depth, mut start) = (Vec::new(), 0, 0);
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                tokens.push(line[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    tokens.push(line[start..].trim());
+    tokens
+}
+
+/// Every distinct ordering of [items], generated in lexicographic order so that repeated items do
+/// not produce repeated orderings.
+fn permutations(mut items: Vec<States>) -> Vec<Vec<States>> {
+    items.sort_unstable();
+    let mut orderings = vec![items.clone()];
+    loop {
+        let Some(pivot) = (1..items.len()).rev().find(|&i| items[i - 1] < items[i]).map(|i| i - 1) else {
+            return orderings;
+        };
+        let successor = (pivot + 1..items.len()).rev().find(|&i| items[i] > items[pivot]).unwrap();
+        items.swap(pivot, successor);
+        items[pivot + 1..].reverse();
+        orderings.push(items.clone());
+    }
+}
+
+/// Parses `@COLORS` lines, each either `state r g b`, or `r1 g1 b1 r2 g2 b2` for a gradient over
+/// the states from `1` up.
+fn parse_colours(lines: &[(usize, &str)], states: u8) -> Result<Vec<Option<u32>>, String> {
+    let mut colours = vec![None; states as usize];
+    let rgb = |c: &[u32]| c[0] << 16 | c[1] << 8 | c[2];
+    for &(number, line) in lines {
+        let values = line
+            .split(|ch: char| ch.is_whitespace() || ch == ',')
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<u32>().ok().filter(|&value| value <= 255))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("line {}: Invalid colour '{line}'.", number + 1))?;
+        match values[..] {
+            [state, ..] if values.len() == 4 => {
+                if let Some(colour) = colours.get_mut(state as usize) {
+                    *colour = Some(rgb(&values[1..]));
+                }
+            }
+            _ if values.len() == 6 => {
+                let last = (states - 1).max(2) as u32;
+                for state in 1..states as u32 {
+                    let mix = |i: usize| (values[i] * (last - state) + values[i + 3] * (state - 1)) / (last - 1);
+                    colours[state as usize] = Some(rgb(&[mix(0), mix(1), mix(2)]));
+                }
+            }
+            _ => return Err(format!("line {}: Expected 4 or 6 numbers, found '{line}'.", number + 1)),
+        }
+    }
+    Ok(colours)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Rule;
+
+    /// Conway's Life as a table, listing each case once and relying on `permute` for the rest.
+    const LIFE: &str = "\
+@RULE LifeTable
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={0,1}
+var c={0,1}
+var d={0,1}
+var e={0,1}
+var f={0,1}
+var g={0,1}
+var h={0,1}
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0
+";
+
+    #[test]
+    fn permuted_life_table_matches_b3_s23() {
+        let table = RuleTable::parse(LIFE).unwrap();
+        let life: Rule = "B3/S23".parse().unwrap();
+        assert_eq!((table.name(), table.states(), table.neighbourhood()), ("LifeTable", 2, Neighbourhood::Moore));
+
+        for cells in 0u16..1 << 9 {
+            let state_at = |dx: isize, dy: isize| {
+                let position = MOORE.iter().position(|&offset| offset == (dx, dy)).unwrap_or(8);
+                (cells >> position & 1) as u8
+            };
+            let (alive, neighbours) = (cells >> 8 & 1 == 1, (cells & 0xFF).count_ones() as u16);
+            let expected = if alive { life.survives(neighbours) } else { life.births(neighbours) };
+            assert_eq!(table.next_state(state_at), expected as u8, "cells {cells:09b}");
+        }
+    }
+
+    #[test]
+    fn loading_a_table_again_reuses_it() {
+        let path = std::env::temp_dir().join(format!("interned-{}.rule", std::process::id()));
+        fs::write(&path, LIFE).unwrap();
+        let load = || path.to_str().unwrap().parse::<Rule>().unwrap();
+        let (first, second) = (load(), load());
+        fs::remove_file(&path).unwrap();
+
+        assert!(std::ptr::eq(first.table().unwrap(), second.table().unwrap()));
+        assert!(std::ptr::eq(intern(RuleTable::parse(LIFE).unwrap()), first.table().unwrap()));
+        assert!(!std::ptr::eq(intern(RuleTable::parse(WIREWORLD).unwrap()), first.table().unwrap()));
+    }
+}